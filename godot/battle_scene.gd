@@ -44,8 +44,10 @@ func _ready():
 	
 	simulator = Match.gd_new(p1_input_type != PlayerType.Player1, p2_input_type != PlayerType.Player2)
 
-var p1_prev_mov: int = 0
-var p2_prev_mov: int = 0
+# What happens when forward and backward are both held, see SocdPolicy in the simulator
+@export var socd_policy: SocdPolicy = SocdPolicy.Neutral
+@onready var p1_decoder := InputDecoder.gd_new(socd_policy)
+@onready var p2_decoder := InputDecoder.gd_new(socd_policy)
 # Called every frame. 'delta' is the elapsed time since the previous frame.
 func _physics_process(delta):
 	var p1_input := get_inputs(p1_input_type)
@@ -93,6 +95,13 @@ enum Result {
 	Timeout,
 }
 
+enum SocdPolicy {
+	Neutral,
+	LastInput,
+	Forward,
+	Backward,
+}
+
 enum PlayerType {
 	Player1,
 	Player2,
@@ -103,34 +112,17 @@ enum PlayerType {
 func get_inputs(type: PlayerType) -> FgInput:
 		match type:
 			PlayerType.Player1:
-				var p1_movement := (Input.is_action_pressed("p1_forward") as int) - (Input.is_action_pressed("p1_backward") as int)
-				var p1_movement_pres := p1_movement if p1_prev_mov != p1_movement else 0
-				p1_prev_mov = p1_movement
-				var p1_attack_press := Input.is_action_just_pressed("p1_attack")
-				var p1_attack_hold := Input.is_action_pressed("p1_attack")
-				var p1_input := FgInput.gd_new(p1_movement, p1_movement_pres, p1_attack_press, p1_attack_hold)
-				return p1_input
+				return p1_decoder.gd_decode(Input.is_action_pressed("p1_forward"), Input.is_action_pressed("p1_backward"), Input.is_action_pressed("p1_attack"))
 			PlayerType.Player2:
-				var p2_movement := (Input.is_action_pressed("p2_forward") as int) - (Input.is_action_pressed("p2_backward") as int)
-				var p2_movement_pres := p2_movement if p2_prev_mov != p2_movement else 0
-				p2_prev_mov = p2_movement
-				var p2_attack_press := Input.is_action_just_pressed("p2_attack")
-				var p2_attack_hold := Input.is_action_pressed("p2_attack")
-				var p2_input := FgInput.gd_new(p2_movement, p2_movement_pres, p2_attack_press, p2_attack_hold)
-				return p2_input
+				return p2_decoder.gd_decode(Input.is_action_pressed("p2_forward"), Input.is_action_pressed("p2_backward"), Input.is_action_pressed("p2_attack"))
 			PlayerType.Ai1:
 				var ai := ai_controller_p1
 				var index := ai.index
 				
 				if  index >= len(ai.move_actions):
 					return  FgInput.gd_new(0, 0, false, false)
-					
-				var p1_movement :int = ai.move_actions[index]
-				var p1_movement_pres :int = p1_movement if p1_prev_mov != p1_movement else 0
-				p1_prev_mov = p1_movement
-				var p1_attack_press :bool = ai.attack_presses[index]
-				var p1_attack_hold :bool = ai.attack_holds[index]
-				var p1_input := FgInput.gd_new(p1_movement, p1_movement_pres, p1_attack_press, p1_attack_hold)
+				
+				var p1_input := p1_decoder.gd_decode_movement(ai.move_actions[index], ai.attack_presses[index], ai.attack_holds[index])
 				
 				ai.index += 1
 				
@@ -141,13 +133,8 @@ func get_inputs(type: PlayerType) -> FgInput:
 				
 				if  index >= len(ai.move_actions):
 					return  FgInput.gd_new(0, 0, false, false)
-					
-				var p2_movement :int = ai.move_actions[index]
-				var p2_movement_pres :int = p2_movement if p2_prev_mov != p2_movement else 0
-				p2_prev_mov = p2_movement
-				var p2_attack_press :bool = ai.attack_presses[index]
-				var p2_attack_hold :bool = ai.attack_holds[index]
-				var p2_input := FgInput.gd_new(p2_movement, p2_movement_pres, p2_attack_press, p2_attack_hold)
+				
+				var p2_input := p2_decoder.gd_decode_movement(ai.move_actions[index], ai.attack_presses[index], ai.attack_holds[index])
 				
 				ai.index += 1
 				
@@ -199,24 +186,5 @@ func game_over():
 	ai_controller_p2.reset()
 
 func update_ai_reward(res: Result, ai: FighterAiController):
-	# step penalty
-	if res == Result.Continue:
-		ai.reward -= 0.15
-		# corner
-		ai.reward -= 0.50 if simulator.player_relative_pos(ai.player1) < 200 else 0
-		# distance penalty
-		ai.reward -= max(simulator.player_distance(), 710) as float ** 0.4 - 13.82
-	
-	ai.reward += 0.30 if simulator.player_hold(ai.player1) >= 30 else 0
-	# ai.reward += 20 if simulator.player_counter(!ai.player1) else 0
-	ai.reward += 20 if simulator.player_hit(!ai.player1) else 0
-	ai.reward += 10 if simulator.player_block(!ai.player1) else 0
-	ai.reward += 10 if simulator.player_block(ai.player1) else 0
-	ai.reward -= 30 if simulator.player_block_ender(!ai.player1) else 0
-	ai.reward += 30 if simulator.player_guard_break(!ai.player1) else 0
-	ai.reward += 100 if simulator.player_dead(!ai.player1) else 0
-	
-	# ai.reward -= 10 if simulator.player_counter(ai.player1) else 0
-	ai.reward -= 20 if simulator.player_hit(ai.player1) else 0
-	ai.reward -= 25 if simulator.player_dead(ai.player1) else 0
+	ai.reward += simulator.ai_reward(res, ai.player1)
 	
@@ -11,17 +11,28 @@ func _ready():
 func _process(delta):
 	queue_redraw()
 
-var collision_p1 = Vector2.DOWN
-var collision_p2 = Vector2.DOWN
-var collision_size = Vector2(125, -153)
+# Where the stage origin is drawn, and how many pixels a stage unit is
+var origin = Vector2(400, 500)
+var stage_scale = 1.0
+
 var collision_color = Color(0.3, 0.3, 0.3, 0.7)
+var hurtbox_color = Color(0.2, 0.3, 0.9, 0.5)
+var hitbox_color = Color(0.9, 0.2, 0.2, 0.6)
 
-var hurtbox_p1 = []
-var hurtbox_p2 = []
-var hurtbox_color = Color(0.3, 0.3, 0.3, 0.7)
+# Simulator boxes have y growing upwards, screen y grows downwards
+func to_screen(rect: Rect2) -> Rect2:
+	var pos = Vector2(rect.position.x, -rect.end.y) * stage_scale + origin
+	return Rect2(pos, rect.size * stage_scale)
 
 # Called every frame to draw
 func _draw():
-	draw_rect(Rect2(collision_p1 + Vector2(400, 500), collision_size * Vector2(1, 1)), collision_color)
-	draw_rect(Rect2(collision_p2 + Vector2(400, 500), collision_size * Vector2(-1, 1)), collision_color)
-	
+	var simulator: Match = battle_scene.simulator
+	if simulator == null:
+		return
+
+	for p1 in [true, false]:
+		draw_rect(to_screen(simulator.player_collision_box(p1)), collision_color)
+		for hurtbox in simulator.player_hurtboxes(p1):
+			draw_rect(to_screen(hurtbox), hurtbox_color)
+		for hitbox in simulator.player_hitboxes(p1):
+			draw_rect(to_screen(hitbox), hitbox_color)
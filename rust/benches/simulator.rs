@@ -0,0 +1,184 @@
+//! Throughput of the simulator, run with `cargo bench --bench simulator`. Criterion reports the
+//! throughput in frames per second as `elem/s`. `step` is `Match::frame_update` without the
+//! conversion of the inputs from Godot objects, which needs the engine running.
+//!
+//! Baseline (release profile, x86_64, a single core of an Intel Xeon cloud vCPU):
+//!
+//! | Benchmark         | Time per iteration | Frames per second |
+//! |-------------------|--------------------|-------------------|
+//! | `step`            | 85 ns              | 11.8 M            |
+//! | `player_obs`      | 67 ns              | -                 |
+//! | `punish_obs`      | 18 ns              | -                 |
+//! | `player_obs_into` | 16 ns              | -                 |
+//! | `punish_obs_into` | 4 ns               | -                 |
+//! | `round`           | 99 µs              | 15.2 M            |
+//! | `batch/64`        | 6.0 µs             | 10.7 M            |
+//!
+//! A regression of more than a few percent on `step` or `batch` slows the training down just as
+//! much, since the simulator runs every environment step.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use footsies_sim::{
+	dataset::MacroAction,
+	decoder::{InputDecoder, SocdPolicy},
+	input::{FgInput, InputConfig},
+	rng::Rng,
+	simul::{self, Match},
+};
+
+/// Matches stepped together in `batch`, like the environments of a training worker.
+const BATCH_SIZE: usize = 64;
+
+/// Both players get the bot input config, like in the training environments.
+fn bot_match() -> Match {
+	Match::new(InputConfig::bot(), InputConfig::bot())
+}
+
+/// Inputs of random agent actions, the way the trained agent plays, so every state gets hit.
+fn random_inputs(frames: usize, seed: u64) -> Vec<[FgInput; 2]> {
+	let mut rng = Rng::new(seed);
+	let mut decoders = [(); 2].map(|_| InputDecoder::new(SocdPolicy::Neutral));
+	let mut holds = [false; 2];
+	let mut res = Vec::with_capacity(frames);
+
+	while res.len() < frames {
+		let actions = [0, 1].map(|i| {
+			let action = MacroAction::ALL
+				[rng.range(0, MacroAction::COUNT as i64 - 1) as usize];
+			let frames = action.frames(holds[i]);
+			holds[i] = action.holds(holds[i]);
+			frames
+		});
+
+		let decode = |decoder: &mut InputDecoder, (movement, press, hold)| {
+			decoder.decode_movement(movement, press, hold)
+		};
+		for (frame1, frame2) in actions[0].into_iter().zip(actions[1]) {
+			res.push([
+				decode(&mut decoders[0], frame1),
+				decode(&mut decoders[1], frame2),
+			]);
+		}
+	}
+
+	res.truncate(frames);
+	res
+}
+
+/// Steps like `battle_scene.gd`, starting a new round or match when one ends.
+#[inline]
+fn advance(sim: &mut Match, [input1, input2]: [FgInput; 2]) -> simul::Result {
+	let res = sim.step(input1, input2);
+
+	if !matches!(res, simul::Result::Continue | simul::Result::Pause) {
+		sim.new_round();
+		if !sim.continues() {
+			*sim = bot_match();
+		}
+	}
+
+	res
+}
+
+/// A match some way into the first round, for the observations.
+fn mid_round() -> Match {
+	let mut sim = bot_match();
+	for inputs in random_inputs(600, 0) {
+		advance(&mut sim, inputs);
+	}
+
+	sim
+}
+
+fn step(c: &mut Criterion) {
+	let inputs = random_inputs(1 << 16, 0);
+	let mut sim = bot_match();
+	let mut i = 0;
+
+	let mut group = c.benchmark_group("step");
+	group.throughput(Throughput::Elements(1));
+	group.bench_function("step", |b| {
+		b.iter(|| {
+			i = (i + 1) % inputs.len();
+			black_box(advance(&mut sim, black_box(inputs[i])))
+		})
+	});
+	group.finish();
+}
+
+fn observations(c: &mut Criterion) {
+	let sim = mid_round();
+
+	c.bench_function("player_obs", |b| {
+		b.iter(|| black_box(&sim).player_obs(black_box(true)))
+	});
+	c.bench_function("punish_obs", |b| {
+		b.iter(|| black_box(&sim).punish_obs(black_box(true)))
+	});
+
+	let mut out = [0.0; Match::PLAYER_OBS_LEN];
+	c.bench_function("player_obs_into", |b| {
+		b.iter(|| black_box(&sim).player_obs_into(black_box(true), black_box(&mut out)))
+	});
+	let mut out = [0.0; Match::PUNISH_OBS_LEN];
+	c.bench_function("punish_obs_into", |b| {
+		b.iter(|| black_box(&sim).punish_obs_into(black_box(true), black_box(&mut out)))
+	});
+}
+
+fn round(c: &mut Criterion) {
+	let inputs = random_inputs(1 << 16, 1);
+
+	// The inputs are the same every time, so is the length of the round
+	let mut sim = bot_match();
+	let frames = inputs
+		.iter()
+		.position(|&inputs| {
+			!matches!(
+				advance(&mut sim, inputs),
+				simul::Result::Continue | simul::Result::Pause
+			)
+		})
+		.expect("the round never ended")
+		+ 1;
+
+	let mut group = c.benchmark_group("round");
+	group.throughput(Throughput::Elements(frames as u64));
+	group.bench_function("round", |b| {
+		b.iter_batched_ref(
+			bot_match,
+			|sim| {
+				for &inputs in &inputs[..frames] {
+					black_box(advance(sim, inputs));
+				}
+			},
+			BatchSize::SmallInput,
+		)
+	});
+	group.finish();
+}
+
+fn batch(c: &mut Criterion) {
+	let inputs: Vec<_> = (0..BATCH_SIZE as u64)
+		.map(|seed| random_inputs(1 << 12, seed))
+		.collect();
+	let mut sims: Vec<_> = (0..BATCH_SIZE).map(|_| bot_match()).collect();
+	let mut i = 0;
+
+	let mut group = c.benchmark_group("batch");
+	group.throughput(Throughput::Elements(BATCH_SIZE as u64));
+	group.bench_function(BATCH_SIZE.to_string(), |b| {
+		b.iter(|| {
+			i = (i + 1) % inputs[0].len();
+			for (sim, inputs) in sims.iter_mut().zip(&inputs) {
+				black_box(advance(sim, black_box(inputs[i])));
+			}
+		})
+	});
+	group.finish();
+}
+
+criterion_group!(benches, step, observations, round, batch);
+criterion_main!(benches);
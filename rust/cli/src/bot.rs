@@ -0,0 +1,112 @@
+use clap::ValueEnum;
+use footsies_sim::{
+	dataset::MacroAction,
+	decoder::{InputDecoder, SocdPolicy},
+	input::FgInput,
+	rng::Rng,
+	simul::Match,
+};
+
+/// Scripted players for headless matches, the trained agent only runs in Godot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BotKind {
+	/// Never moves
+	Idle,
+	/// Random agent actions
+	Random,
+	/// Walks in and attacks when close
+	Rush,
+}
+
+/// Picks an agent action every `MacroAction::LEN` frames and plays it like `battle_scene.gd`.
+#[derive(Debug, Clone)]
+pub struct Bot {
+	kind: BotKind,
+	p1: bool,
+	rng: Rng,
+	decoder: InputDecoder,
+	frames: [(i8, bool, bool); MacroAction::LEN],
+	frame: usize,
+	holds: bool,
+}
+
+impl Bot {
+	/// Distance the rush bot starts attacking from
+	const RUSH_RANGE: i16 = 300;
+
+	pub const fn new(kind: BotKind, p1: bool, seed: u64) -> Self {
+		Bot {
+			kind,
+			p1,
+			rng: Rng::new(seed),
+			decoder: InputDecoder::new(SocdPolicy::Neutral),
+			frames: [(0, false, false); MacroAction::LEN],
+			frame: MacroAction::LEN,
+			holds: false,
+		}
+	}
+
+	pub fn input(&mut self, sim: &Match) -> FgInput {
+		if self.frame == MacroAction::LEN {
+			let action = self.decide(sim);
+			self.frames = action.frames(self.holds);
+			self.holds = action.holds(self.holds);
+			self.frame = 0;
+		}
+
+		let (movement, attack_press, attack_hold) = self.frames[self.frame];
+		self.frame += 1;
+
+		self.decoder
+			.decode_movement(movement, attack_press, attack_hold)
+	}
+
+	fn decide(&mut self, sim: &Match) -> MacroAction {
+		match self.kind {
+			BotKind::Idle => MacroAction::None,
+			BotKind::Random => {
+				MacroAction::ALL
+					[self.rng.range(0, MacroAction::COUNT as i64 - 1) as usize]
+			}
+			BotKind::Rush if !sim.player(self.p1).can_attack() => MacroAction::None,
+			BotKind::Rush if sim.player_distance() > Self::RUSH_RANGE => {
+				MacroAction::FWalk
+			}
+			BotKind::Rush => match self.rng.range(0, 3) {
+				0 | 1 => MacroAction::NAttack,
+				2 => MacroAction::MAttack,
+				_ => MacroAction::BDash,
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{Bot, BotKind};
+	use footsies_sim::{
+		input::InputConfig,
+		simul::{self, Match},
+	};
+
+	#[test]
+	fn rush() {
+		let mut sim = Match::new(InputConfig::bot(), InputConfig::bot());
+		let mut rush = Bot::new(BotKind::Rush, true, 0);
+		let mut idle = Bot::new(BotKind::Idle, false, 0);
+
+		for _ in 0..5000 {
+			let (input1, input2) = (rush.input(&sim), idle.input(&sim));
+			if sim.p1_wins() > 0 {
+				return;
+			}
+
+			let res = sim.step(input1, input2);
+			if !matches!(res, simul::Result::Continue | simul::Result::Pause) {
+				sim.new_round();
+			}
+		}
+
+		panic!("the rush bot never won a round");
+	}
+}
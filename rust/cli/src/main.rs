@@ -0,0 +1,533 @@
+mod bot;
+mod tui;
+
+use std::{
+	error::Error,
+	fs,
+	io::{self, BufWriter, Write},
+	path::{Path, PathBuf},
+	process::ExitCode,
+	time::Instant,
+};
+
+use bot::{Bot, BotKind};
+use clap::{Parser, Subcommand, ValueEnum};
+use footsies_sim::{
+	framedata::{self, MoveData},
+	input::{FgInput, InputConfig},
+	player::PlayerState,
+	render::{Renderer, SpriteSheet},
+	replay::Replay,
+	simul::{self, Match},
+	stats::MatchStats,
+};
+use serde::Serialize;
+use serde_json::json;
+
+/// Runs the FOOTSIES simulator without Godot.
+#[derive(Debug, Parser)]
+#[command(name = "footsies-cli", version)]
+struct Cli {
+	#[command(subcommand)]
+	command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+	/// Plays a match between two bots and prints the result and stats as JSON
+	Sim {
+		#[arg(long, value_enum, default_value_t = BotKind::Random)]
+		p1: BotKind,
+		#[arg(long, value_enum, default_value_t = BotKind::Random)]
+		p2: BotKind,
+		#[arg(long, default_value_t = 0)]
+		seed: u64,
+		/// Gives up on the match after this many frames
+		#[arg(long, default_value_t = 100_000)]
+		max_frames: usize,
+		/// Saves the match as a replay
+		#[arg(long)]
+		replay: Option<PathBuf>,
+	},
+	/// Plays a replay back twice and checks that both runs end in the same state
+	Verify {
+		replay: PathBuf,
+		/// State hash the replay must end with, as printed by `sim`
+		#[arg(long)]
+		hash: Option<i64>,
+	},
+	/// Prints the state of every frame of a replay
+	Dump {
+		replay: PathBuf,
+		#[arg(long, value_enum, default_value_t = Format::Json)]
+		format: Format,
+	},
+	/// Prints the frame data of every move
+	Framedata {
+		#[arg(long, value_enum, default_value_t = Format::Csv)]
+		format: Format,
+	},
+	/// Plays a match in the terminal, against a bot or a second player on the same keyboard
+	Play {
+		/// Player 2 is a human when not given
+		#[arg(long, value_enum)]
+		bot: Option<BotKind>,
+		#[arg(long, default_value_t = 0)]
+		seed: u64,
+	},
+	/// Watches two bots or a replay in the terminal
+	Watch {
+		#[arg(long, value_enum, default_value_t = BotKind::Random)]
+		p1: BotKind,
+		#[arg(long, value_enum, default_value_t = BotKind::Random)]
+		p2: BotKind,
+		#[arg(long, default_value_t = 0)]
+		seed: u64,
+		/// Watches the replay instead of the bots
+		#[arg(long)]
+		replay: Option<PathBuf>,
+	},
+	/// Draws a replay into an animated GIF, or into a folder of PNGs
+	Render {
+		replay: PathBuf,
+		/// A GIF if it ends with `.gif`, a folder of PNGs otherwise
+		output: PathBuf,
+		#[arg(long, default_value_t = 640, value_parser = clap::value_parser!(u16).range(1..))]
+		width: u16,
+		#[arg(long, default_value_t = 360, value_parser = clap::value_parser!(u16).range(1..))]
+		height: u16,
+		/// Draws only every this many frames
+		#[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+		every: u64,
+		/// Folder of the fighter sprites, like `godot/art/fighter`. Players are drawn as boxes
+		/// when not given
+		#[arg(long)]
+		sprites: Option<PathBuf>,
+	},
+	/// Measures how many frames per second the simulator runs, with random bots
+	Bench {
+		#[arg(long, default_value_t = 1_000_000)]
+		frames: usize,
+		#[arg(long, default_value_t = 0)]
+		seed: u64,
+	},
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+	Json,
+	Csv,
+}
+
+fn main() -> ExitCode {
+	let cli = Cli::parse();
+	let stdout = io::stdout();
+	let mut out = BufWriter::new(stdout.lock());
+
+	let res = match cli.command {
+		Command::Sim {
+			p1,
+			p2,
+			seed,
+			max_frames,
+			replay,
+		} => sim(&mut out, p1, p2, seed, max_frames, replay.as_deref()),
+		Command::Verify { replay, hash } => verify(&mut out, &replay, hash),
+		Command::Dump { replay, format } => dump(&mut out, &replay, format),
+		Command::Framedata { format } => frame_data(&mut out, format),
+		Command::Render {
+			replay,
+			output,
+			width,
+			height,
+			every,
+			sprites,
+		} => render(
+			&replay,
+			&output,
+			[width, height],
+			every as usize,
+			sprites.as_deref(),
+		),
+		Command::Bench { frames, seed } => bench(&mut out, frames, seed),
+		Command::Play { bot, seed } => play(bot, seed),
+		Command::Watch {
+			p1,
+			p2,
+			seed,
+			replay,
+		} => watch(p1, p2, seed, replay.as_deref()),
+	};
+
+	match res.and_then(|code| out.flush().map(|_| code).map_err(Into::into)) {
+		Ok(code) => code,
+		Err(err) => {
+			eprintln!("error: {err}");
+			ExitCode::FAILURE
+		}
+	}
+}
+
+type CliResult = Result<ExitCode, Box<dyn Error>>;
+
+#[inline]
+fn round_over(res: simul::Result) -> bool {
+	!matches!(res, simul::Result::Continue | simul::Result::Pause)
+}
+
+fn sim(
+	out: &mut impl Write,
+	p1: BotKind,
+	p2: BotKind,
+	seed: u64,
+	max_frames: usize,
+	replay_path: Option<&Path>,
+) -> CliResult {
+	let mut sim = Match::new(InputConfig::bot(), InputConfig::bot());
+	sim.set_seed(seed as i64);
+	let mut replay = Replay::new(InputConfig::bot(), InputConfig::bot());
+	replay.set_seed(seed as i64);
+
+	let mut bots = [
+		Bot::new(p1, true, seed),
+		Bot::new(p2, false, seed.wrapping_add(1)),
+	];
+	let mut stats = MatchStats::new();
+	let mut res = simul::Result::Continue;
+	let mut hash = sim.state_hash();
+
+	for _ in 0..max_frames {
+		let [input1, input2] = bots.each_mut().map(|bot| bot.input(&sim));
+		replay.push(input1, input2);
+
+		res = sim.step(input1, input2);
+		stats.record(&sim, res);
+		hash = sim.state_hash();
+
+		if round_over(res) {
+			sim.new_round();
+			if !sim.continues() {
+				break;
+			}
+		}
+	}
+
+	if let Some(path) = replay_path {
+		fs::write(path, serde_json::to_string(&replay)?)?;
+	}
+
+	let summary = json!({
+		"result": res,
+		"finished": !sim.continues(),
+		"wins": [sim.p1_wins(), sim.p2_wins()],
+		"frames": replay.frames(),
+		"hash": hash,
+		"stats": stats,
+	});
+	writeln!(out, "{}", serde_json::to_string_pretty(&summary)?)?;
+
+	Ok(ExitCode::SUCCESS)
+}
+
+fn load_replay(path: &Path) -> Result<Replay, Box<dyn Error>> {
+	Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+struct Playback {
+	frames: usize,
+	result: simul::Result,
+	wins: [u8; 2],
+	hash: i64,
+}
+
+fn play_back(replay: &Replay) -> Playback {
+	let mut res = Playback {
+		frames: 0,
+		result: simul::Result::Continue,
+		wins: [0, 0],
+		hash: replay.start().state_hash(),
+	};
+
+	replay.play(|sim, result| {
+		res = Playback {
+			frames: res.frames + 1,
+			result,
+			wins: [sim.p1_wins(), sim.p2_wins()],
+			hash: sim.state_hash(),
+		};
+	});
+
+	res
+}
+
+fn verify(out: &mut impl Write, path: &Path, hash: Option<i64>) -> CliResult {
+	let replay = load_replay(path)?;
+	let first = play_back(&replay);
+	let second = play_back(&replay);
+
+	let deterministic = first == second;
+	let hash_matches = hash.is_none_or(|hash| hash == first.hash);
+
+	let summary = json!({
+		"playback": first,
+		"inputs": replay.inputs.len(),
+		"deterministic": deterministic,
+		"hash_matches": hash_matches,
+	});
+	writeln!(out, "{}", serde_json::to_string_pretty(&summary)?)?;
+
+	Ok(match deterministic && hash_matches {
+		true => ExitCode::SUCCESS,
+		false => ExitCode::FAILURE,
+	})
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+struct PlayerFrame {
+	input: FgInput,
+	position: i16,
+	guard: u8,
+	state: PlayerState,
+	hold: u8,
+	wins: u8,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+struct Frame {
+	frame: usize,
+	result: simul::Result,
+	/// `GameState` as in `Match::state`
+	state: i64,
+	timer: u16,
+	players: [PlayerFrame; 2],
+}
+
+impl Frame {
+	const CSV_HEADER: &str = "frame,result,state,timer,\
+		p1_movement,p1_movement_press,p1_attack_press,p1_attack_hold,\
+		p1_position,p1_guard,p1_state,p1_hold,p1_wins,\
+		p2_movement,p2_movement_press,p2_attack_press,p2_attack_hold,\
+		p2_position,p2_guard,p2_state,p2_hold,p2_wins";
+
+	fn write_csv(&self, out: &mut impl Write) -> io::Result<()> {
+		write!(
+			out,
+			"{},{:?},{},{}",
+			self.frame, self.result, self.state, self.timer
+		)?;
+		for player in &self.players {
+			let input = player.input;
+			// States like `NNormal(3, false)` have a comma, so they are quoted
+			write!(
+				out,
+				",{},{},{},{},{},{},\"{:?}\",{},{}",
+				input.movement,
+				input.movement_press,
+				input.attack_press,
+				input.attack_hold,
+				player.position,
+				player.guard,
+				player.state,
+				player.hold,
+				player.wins
+			)?;
+		}
+		writeln!(out)
+	}
+}
+
+fn dump(out: &mut impl Write, path: &Path, format: Format) -> CliResult {
+	let replay = load_replay(path)?;
+	let mut frames = Vec::with_capacity(replay.inputs.len());
+
+	replay.play(|sim, result| {
+		let inputs = replay.inputs[frames.len()];
+		let player = |p1: bool| PlayerFrame {
+			input: inputs[!p1 as usize],
+			position: sim.player(p1).position,
+			guard: sim.player_guard(p1),
+			state: sim.player(p1).state(),
+			hold: sim.player_hold(p1),
+			wins: sim.player(p1).wins,
+		};
+
+		frames.push(Frame {
+			frame: frames.len(),
+			result,
+			state: sim.state(),
+			timer: sim.timer_sec(),
+			players: [player(true), player(false)],
+		});
+	});
+
+	match format {
+		Format::Json => writeln!(out, "{}", serde_json::to_string(&frames)?)?,
+		Format::Csv => {
+			writeln!(out, "{}", Frame::CSV_HEADER)?;
+			for frame in &frames {
+				frame.write_csv(out)?;
+			}
+		}
+	}
+
+	Ok(ExitCode::SUCCESS)
+}
+
+/// Frame data of a move, frames are counted from 1 like in fighting games.
+#[derive(Debug, Clone, Serialize)]
+struct MoveSummary {
+	name: &'static str,
+	total: u32,
+	startup: Option<u32>,
+	active: Option<u32>,
+	recovery: Option<u32>,
+	cancelable: bool,
+	ender: bool,
+	low: bool,
+}
+
+impl MoveSummary {
+	fn new(name: &'static str, data: &[MoveData]) -> Self {
+		let total = data.iter().map(|frame| frame.duration as u32).sum();
+		let mut first_active = None;
+		let mut last_active = None;
+		let mut frame = 0;
+
+		for data in data {
+			if data.data.hitbox.is_some() {
+				first_active.get_or_insert(frame + 1);
+				last_active = Some(frame + data.duration as u32);
+			}
+			frame += data.duration as u32;
+		}
+
+		MoveSummary {
+			name,
+			total,
+			startup: first_active,
+			active: first_active
+				.zip(last_active)
+				.map(|(first, last)| last - first + 1),
+			recovery: last_active.map(|last| total - last),
+			cancelable: data.iter().any(|frame| frame.data.cancel),
+			ender: data.iter().any(|frame| frame.data.ender),
+			low: data.iter().any(|frame| frame.data.low),
+		}
+	}
+}
+
+fn frame_data(out: &mut impl Write, format: Format) -> CliResult {
+	let moves = [
+		MoveSummary::new("idle", &framedata::IDLE_DATA),
+		MoveSummary::new("fwalk", &framedata::FWALK_DATA),
+		MoveSummary::new("bwalk", &framedata::BWALK_DATA),
+		MoveSummary::new("fdash", &framedata::FDASH_DATA),
+		MoveSummary::new("bdash", &framedata::BDASH_DATA),
+		MoveSummary::new("nnormal", &framedata::NNORMAL_DATA),
+		MoveSummary::new("mnormal", &framedata::MNORMAL_DATA),
+		MoveSummary::new("nspecial", &framedata::NSPECIAL_DATA),
+		MoveSummary::new("mspecial", &framedata::MSPECIAL_DATA),
+		MoveSummary::new("hit", &framedata::HIT_DATA),
+		MoveSummary::new("hblock", &framedata::HBLOCK_DATA),
+		MoveSummary::new("lblock", &framedata::LBLOCK_DATA),
+		MoveSummary::new("guard_break", &framedata::GUARD_BREAK_DATA),
+	];
+
+	match format {
+		Format::Json => writeln!(out, "{}", serde_json::to_string_pretty(&moves)?)?,
+		Format::Csv => {
+			let field = |x: Option<u32>| x.map_or(String::new(), |x| x.to_string());
+
+			writeln!(
+				out,
+				"move,total,startup,active,recovery,cancelable,ender,low"
+			)?;
+			for m in &moves {
+				writeln!(
+					out,
+					"{},{},{},{},{},{},{},{}",
+					m.name,
+					m.total,
+					field(m.startup),
+					field(m.active),
+					field(m.recovery),
+					m.cancelable,
+					m.ender,
+					m.low
+				)?;
+			}
+		}
+	}
+
+	Ok(ExitCode::SUCCESS)
+}
+
+fn play(bot: Option<BotKind>, seed: u64) -> CliResult {
+	tui::play(bot, seed)?;
+
+	Ok(ExitCode::SUCCESS)
+}
+
+fn watch(p1: BotKind, p2: BotKind, seed: u64, replay: Option<&Path>) -> CliResult {
+	match replay {
+		Some(path) => tui::watch_replay(load_replay(path)?)?,
+		None => tui::watch(p1, p2, seed)?,
+	}
+
+	Ok(ExitCode::SUCCESS)
+}
+
+fn render(
+	path: &Path,
+	output: &Path,
+	[width, height]: [u16; 2],
+	every: usize,
+	sprite_dir: Option<&Path>,
+) -> CliResult {
+	let replay = load_replay(path)?;
+	let mut renderer = Renderer::new(width as usize, height as usize);
+	if let Some(dir) = sprite_dir {
+		renderer.sprites = Some(SpriteSheet::load_dir(dir)?);
+	}
+
+	if output.extension().is_some_and(|ext| ext == "gif") {
+		let file = fs::File::create(output)?;
+		renderer.render_replay_gif(&replay, BufWriter::new(file), every)?;
+	} else {
+		fs::create_dir_all(output)?;
+		renderer.render_replay_pngs(&replay, output, every)?;
+	}
+
+	Ok(ExitCode::SUCCESS)
+}
+
+fn bench(out: &mut impl Write, frames: usize, seed: u64) -> CliResult {
+	let mut sim = Match::new(InputConfig::bot(), InputConfig::bot());
+	sim.set_seed(seed as i64);
+	let mut bots = [
+		Bot::new(BotKind::Random, true, seed),
+		Bot::new(BotKind::Random, false, seed.wrapping_add(1)),
+	];
+
+	let start = Instant::now();
+	for _ in 0..frames {
+		let [input1, input2] = bots.each_mut().map(|bot| bot.input(&sim));
+
+		if round_over(sim.step(input1, input2)) {
+			sim.new_round();
+			if !sim.continues() {
+				sim = Match::new(InputConfig::bot(), InputConfig::bot());
+			}
+		}
+	}
+	let secs = start.elapsed().as_secs_f64();
+
+	writeln!(
+		out,
+		"{frames} frames in {secs:.3} s, {:.0} frames/s",
+		frames as f64 / secs
+	)?;
+
+	Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,529 @@
+use std::{
+	collections::HashMap,
+	io,
+	time::{Duration, Instant},
+};
+
+use crossterm::{
+	event::{
+		self, Event, KeyCode, KeyEvent, KeyEventKind, KeyboardEnhancementFlags,
+		PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+	},
+	execute,
+	terminal::supports_keyboard_enhancement,
+};
+use footsies_sim::{
+	decoder::{InputDecoder, RawInput, SocdPolicy},
+	framedata::WorldBox,
+	input::{FgInput, InputConfig},
+	player::PlayerState,
+	replay::Replay,
+	simul::{self, Match},
+};
+use ratatui::{
+	buffer::Buffer,
+	layout::{Alignment, Rect},
+	style::{Color, Modifier, Style},
+	text::{Line, Span},
+	widgets::{Block, Paragraph, Widget},
+	DefaultTerminal,
+};
+
+use crate::bot::{Bot, BotKind};
+
+/// The simulation runs at 60 frames per second, like `_physics_process` in Godot.
+const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// Keys of a human player, the same as the Godot input map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Keys {
+	pub forward: KeyCode,
+	pub backward: KeyCode,
+	pub attack: KeyCode,
+}
+
+impl Keys {
+	pub const P1: Keys = Keys {
+		forward: KeyCode::Char('d'),
+		backward: KeyCode::Char('a'),
+		attack: KeyCode::Char(' '),
+	};
+	pub const P2: Keys = Keys {
+		forward: KeyCode::Left,
+		backward: KeyCode::Right,
+		attack: KeyCode::Enter,
+	};
+}
+
+/// Which keys are held down. Most terminals only report presses and repeats, so without the
+/// kitty keyboard protocol a key counts as held for `Keyboard::HOLD_TIME` after its last press.
+/// Holding a key then drops out until the terminal starts repeating it.
+#[derive(Debug, Clone)]
+pub struct Keyboard {
+	held: HashMap<KeyCode, Instant>,
+	releases: bool,
+}
+
+impl Keyboard {
+	const HOLD_TIME: Duration = Duration::from_millis(150);
+
+	pub fn new(releases: bool) -> Self {
+		Keyboard {
+			held: HashMap::new(),
+			releases,
+		}
+	}
+
+	pub fn handle(&mut self, event: KeyEvent, now: Instant) {
+		// Shift and caps lock should not stop the player
+		let code = match event.code {
+			KeyCode::Char(c) => KeyCode::Char(c.to_ascii_lowercase()),
+			code => code,
+		};
+
+		match event.kind {
+			KeyEventKind::Press | KeyEventKind::Repeat => {
+				self.held.insert(code, now);
+			}
+			KeyEventKind::Release => {
+				self.held.remove(&code);
+			}
+		}
+	}
+
+	pub fn is_held(&self, code: KeyCode, now: Instant) -> bool {
+		self.held.get(&code).is_some_and(|&time| {
+			self.releases || now.saturating_duration_since(time) < Self::HOLD_TIME
+		})
+	}
+
+	pub fn raw_input(&self, keys: Keys, now: Instant) -> RawInput {
+		RawInput::new(
+			self.is_held(keys.forward, now),
+			self.is_held(keys.backward, now),
+			self.is_held(keys.attack, now),
+		)
+	}
+}
+
+/// Who plays one side of a live match.
+#[derive(Debug, Clone)]
+pub enum Controller {
+	Human { keys: Keys, decoder: InputDecoder },
+	Bot(Bot),
+}
+
+impl Controller {
+	pub const fn human(keys: Keys) -> Self {
+		Controller::Human {
+			keys,
+			decoder: InputDecoder::new(SocdPolicy::Neutral),
+		}
+	}
+
+	#[inline]
+	pub const fn is_bot(&self) -> bool {
+		matches!(self, Controller::Bot(_))
+	}
+
+	#[inline]
+	pub const fn input_config(&self) -> InputConfig {
+		InputConfig::preset(self.is_bot())
+	}
+
+	fn input(&mut self, sim: &Match, keyboard: &Keyboard, now: Instant) -> FgInput {
+		match self {
+			Controller::Human { keys, decoder } => {
+				decoder.decode(keyboard.raw_input(*keys, now))
+			}
+			Controller::Bot(bot) => bot.input(sim),
+		}
+	}
+}
+
+/// Where the inputs of the match come from.
+#[derive(Debug, Clone)]
+pub enum Source {
+	Live([Controller; 2]),
+	Replay { replay: Replay, next: usize },
+}
+
+/// A match on screen, stepped once per `FRAME_TIME`.
+#[derive(Debug, Clone)]
+pub struct App {
+	sim: Match,
+	source: Source,
+	/// For restarts, bots replay the same match
+	initial: Source,
+	paused: bool,
+	quit: bool,
+}
+
+impl App {
+	pub fn new(source: Source) -> Self {
+		App {
+			sim: Self::start(&source),
+			initial: source.clone(),
+			source,
+			paused: false,
+			quit: false,
+		}
+	}
+
+	/// Bots are given the bot input config, the same as `battle_scene.gd`.
+	fn start(source: &Source) -> Match {
+		match source {
+			Source::Live([p1, p2]) => Match::new(p1.input_config(), p2.input_config()),
+			Source::Replay { replay, .. } => replay.start(),
+		}
+	}
+
+	/// Starts the match over, the replay from its first frame.
+	pub fn restart(&mut self) {
+		self.source = self.initial.clone();
+		self.sim = Self::start(&self.source);
+	}
+
+	/// Advances the match by a frame, unless it is paused, over or the replay ran out.
+	pub fn step(&mut self, keyboard: &Keyboard, now: Instant) {
+		if self.paused || !self.sim.continues() {
+			return;
+		}
+
+		let [input1, input2] = match &mut self.source {
+			Source::Live(controllers) => controllers
+				.each_mut()
+				.map(|c| c.input(&self.sim, keyboard, now)),
+			Source::Replay { replay, next } => {
+				let Some(&inputs) = replay.inputs.get(*next) else {
+					return;
+				};
+				*next += 1;
+				inputs
+			}
+		};
+
+		let res = self.sim.step(input1, input2);
+		if !matches!(res, simul::Result::Continue | simul::Result::Pause) {
+			self.sim.new_round();
+		}
+	}
+
+	/// Keys that control the app rather than a player.
+	fn handle(&mut self, event: KeyEvent) {
+		if event.kind == KeyEventKind::Release {
+			return;
+		}
+
+		match event.code {
+			KeyCode::Esc | KeyCode::Char('q') => self.quit = true,
+			KeyCode::Char('p') => self.paused = !self.paused,
+			KeyCode::Char('r') => self.restart(),
+			_ => (),
+		}
+	}
+
+	/// Runs until the player quits, drawing every frame.
+	pub fn run(mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+		// Key releases are needed to hold attack, the kitty protocol reports them
+		let releases = supports_keyboard_enhancement().unwrap_or(false);
+		if releases {
+			execute!(
+				io::stdout(),
+				PushKeyboardEnhancementFlags(
+					KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+				)
+			)?;
+		}
+
+		let mut keyboard = Keyboard::new(releases);
+		let mut next_frame = Instant::now();
+		let res = loop {
+			if let Err(err) =
+				terminal.draw(|frame| frame.render_widget(&self, frame.area()))
+			{
+				break Err(err);
+			}
+
+			if let Err(err) = self.read_keys(&mut keyboard, next_frame) {
+				break Err(err);
+			}
+
+			if self.quit {
+				break Ok(());
+			}
+
+			let now = Instant::now();
+			self.step(&keyboard, now);
+			// Falls behind instead of catching up with a burst of frames
+			next_frame = (next_frame + FRAME_TIME).max(now);
+		};
+
+		if releases {
+			execute!(io::stdout(), PopKeyboardEnhancementFlags)?;
+		}
+
+		res
+	}
+
+	/// Reads keys until the frame is due, so the match does not slow down with them.
+	fn read_keys(&mut self, keyboard: &mut Keyboard, until: Instant) -> io::Result<()> {
+		let mut now = Instant::now();
+		while now < until {
+			if event::poll(until - now)? {
+				if let Event::Key(key) = event::read()? {
+					keyboard.handle(key, Instant::now());
+					self.handle(key);
+				}
+			}
+			now = Instant::now();
+		}
+
+		Ok(())
+	}
+
+	fn status(&self) -> &'static str {
+		match (self.paused, self.sim.continues(), self.sim.p1_wins() >= 3) {
+			(true, ..) => "Paused",
+			(_, false, true) => "Player 1 wins",
+			(_, false, false) => "Player 2 wins",
+			_ => match &self.source {
+				Source::Replay { replay, next } if *next >= replay.inputs.len() => {
+					"End of replay"
+				}
+				_ => "",
+			},
+		}
+	}
+
+	fn help(&self) -> &'static str {
+		match &self.source {
+			Source::Live([p1, p2]) => match (p1.is_bot(), p2.is_bot()) {
+				(false, false) => {
+					"P1: A D Space   P2: ← → Enter   p pause  r restart  q quit"
+				}
+				(false, true) => {
+					"A D move  Space attack   p pause  r restart  q quit"
+				}
+				_ => "p pause  r restart  q quit",
+			},
+			Source::Replay { .. } => "p pause  r restart  q quit",
+		}
+	}
+}
+
+const P1_COLOR: Color = Color::LightBlue;
+const P2_COLOR: Color = Color::LightRed;
+const HITBOX_COLOR: Color = Color::Yellow;
+
+/// Name of the state without its frame counter.
+fn state_name(state: PlayerState) -> &'static str {
+	match state {
+		PlayerState::Idle(_) => "Idle",
+		PlayerState::FWalk(_) => "FWalk",
+		PlayerState::BWalk(_) => "BWalk",
+		PlayerState::FDash(_) => "FDash",
+		PlayerState::BDash(_) => "BDash",
+		PlayerState::HBlock(..) => "HBlock",
+		PlayerState::LBlock(..) => "LBlock",
+		PlayerState::GuardBreak(_) => "GuardBreak",
+		PlayerState::Hit(_) => "Hit",
+		PlayerState::NNormal(..) => "NNormal",
+		PlayerState::MNormal(..) => "MNormal",
+		PlayerState::NSpecial(..) => "NSpecial",
+		PlayerState::MSpecial(..) => "MSpecial",
+		PlayerState::Dead(_) => "Dead",
+	}
+}
+
+/// Columns of the stage a box covers, the stage is scaled to `width`.
+fn columns(b: WorldBox, width: u16) -> std::ops::RangeInclusive<u16> {
+	let column = |x: i16| {
+		let x = x.clamp(0, Match::STAGE_LEN) as u32;
+		(x * (width as u32 - 1) / Match::STAGE_LEN as u32) as u16
+	};
+
+	column(b.x1.min(b.x2))..=column(b.x1.max(b.x2))
+}
+
+impl Widget for &App {
+	fn render(self, area: Rect, buf: &mut Buffer) {
+		let block = Block::bordered()
+			.title(" FOOTSIES ")
+			.title_alignment(Alignment::Center);
+		let inner = block.inner(area);
+		block.render(area, buf);
+
+		if inner.width < 8 || inner.height < 6 {
+			return;
+		}
+
+		let sim = &self.sim;
+		let rounds = |wins: u8| {
+			(0..3).map(|i| if i < wins { '●' } else { '○' })
+				.collect::<String>()
+		};
+		let guard = |p1: bool| "■".repeat(sim.player_guard(p1) as usize);
+		let bold = Style::new().add_modifier(Modifier::BOLD);
+
+		let header = Line::from(vec![
+			Span::styled("P1 ", bold.fg(P1_COLOR)),
+			Span::raw(rounds(sim.p1_wins())),
+			Span::raw("  "),
+			Span::styled(guard(true), Style::new().fg(P1_COLOR)),
+		]);
+		Paragraph::new(header).render(Rect { height: 1, ..inner }, buf);
+
+		let header = Line::from(vec![
+			Span::styled(guard(false), Style::new().fg(P2_COLOR)),
+			Span::raw("  "),
+			Span::raw(rounds(sim.p2_wins())),
+			Span::styled(" P2", bold.fg(P2_COLOR)),
+		]);
+		Paragraph::new(header)
+			.alignment(Alignment::Right)
+			.render(Rect { height: 1, ..inner }, buf);
+
+		Paragraph::new(Span::styled(sim.timer_sec().to_string(), bold))
+			.alignment(Alignment::Center)
+			.render(Rect { height: 1, ..inner }, buf);
+
+		// Labels, bodies and the floor sit at the bottom of the area, above the help line
+		let floor = inner.bottom() - 3;
+		let body = floor - 1;
+		let label = body - 1;
+
+		for x in inner.left()..inner.right() {
+			buf[(x, floor)].set_char('▀').set_fg(Color::DarkGray);
+		}
+
+		for (p1, color) in [(true, P1_COLOR), (false, P2_COLOR)] {
+			let cols = columns(sim.collision_box(p1), inner.width);
+			for x in cols.clone() {
+				buf[(inner.x + x, body)].set_char('█').set_fg(color);
+			}
+			if let Some(hitbox) = sim.hitbox(p1) {
+				for x in columns(hitbox, inner.width) {
+					buf[(inner.x + x, body)].set_char('═').set_fg(HITBOX_COLOR);
+				}
+			}
+
+			// Centered on the body, kept inside the stage
+			let name = state_name(sim.player(p1).state());
+			let center = (cols.start() + cols.end()) / 2;
+			let x = center
+				.saturating_sub(name.len() as u16 / 2)
+				.min(inner.width.saturating_sub(name.len() as u16));
+			buf.set_string(inner.x + x, label, name, Style::new().fg(color));
+		}
+
+		Paragraph::new(Span::styled(self.status(), bold))
+			.alignment(Alignment::Center)
+			.render(
+				Rect {
+					y: inner.y + 2,
+					height: 1,
+					..inner
+				},
+				buf,
+			);
+		Paragraph::new(Span::styled(self.help(), Style::new().fg(Color::DarkGray)))
+			.alignment(Alignment::Center)
+			.render(
+				Rect {
+					y: inner.bottom() - 1,
+					height: 1,
+					..inner
+				},
+				buf,
+			);
+	}
+}
+
+/// Plays `source` in the terminal until the player quits.
+pub fn run(source: Source) -> io::Result<()> {
+	let mut terminal = ratatui::init();
+	let res = App::new(source).run(&mut terminal);
+	ratatui::restore();
+
+	res
+}
+
+/// The second player is a human when `bot` is `None`.
+pub fn play(bot: Option<BotKind>, seed: u64) -> io::Result<()> {
+	let p2 = match bot {
+		Some(kind) => Controller::Bot(Bot::new(kind, false, seed)),
+		None => Controller::human(Keys::P2),
+	};
+
+	run(Source::Live([Controller::human(Keys::P1), p2]))
+}
+
+/// Bot against bot.
+pub fn watch(p1: BotKind, p2: BotKind, seed: u64) -> io::Result<()> {
+	run(Source::Live([
+		Controller::Bot(Bot::new(p1, true, seed)),
+		Controller::Bot(Bot::new(p2, false, seed.wrapping_add(1))),
+	]))
+}
+
+pub fn watch_replay(replay: Replay) -> io::Result<()> {
+	run(Source::Replay { replay, next: 0 })
+}
+
+#[cfg(test)]
+mod test {
+	use std::time::{Duration, Instant};
+
+	use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+	use ratatui::{buffer::Buffer, layout::Rect, widgets::Widget};
+
+	use super::{App, Controller, Keyboard, Keys, Source};
+	use crate::bot::{Bot, BotKind};
+
+	fn key(code: KeyCode, kind: KeyEventKind) -> KeyEvent {
+		KeyEvent::new_with_kind(code, KeyModifiers::NONE, kind)
+	}
+
+	#[test]
+	fn keyboard() {
+		let now = Instant::now();
+		let later = now + Duration::from_secs(1);
+
+		let mut fallback = Keyboard::new(false);
+		fallback.handle(key(KeyCode::Char('D'), KeyEventKind::Press), now);
+		assert!(fallback.raw_input(Keys::P1, now).forward);
+		assert!(!fallback.raw_input(Keys::P1, later).forward);
+
+		let mut kitty = Keyboard::new(true);
+		kitty.handle(key(KeyCode::Char(' '), KeyEventKind::Press), now);
+		assert!(kitty.raw_input(Keys::P1, later).attack);
+		kitty.handle(key(KeyCode::Char(' '), KeyEventKind::Release), later);
+		assert!(!kitty.raw_input(Keys::P1, later).attack);
+	}
+
+	#[test]
+	fn render() {
+		let mut app = App::new(Source::Live([
+			Controller::Bot(Bot::new(BotKind::Idle, true, 0)),
+			Controller::Bot(Bot::new(BotKind::Idle, false, 0)),
+		]));
+		let keyboard = Keyboard::new(false);
+		for _ in 0..120 {
+			app.step(&keyboard, Instant::now());
+		}
+
+		let area = Rect::new(0, 0, 80, 12);
+		let mut buf = Buffer::empty(area);
+		(&app).render(area, &mut buf);
+
+		let rows: Vec<String> = (0..area.height)
+			.map(|y| (0..area.width).map(|x| buf[(x, y)].symbol()).collect())
+			.collect();
+		assert!(rows[1].starts_with("│P1 ○○○  ■■■"));
+		assert!(rows[1].ends_with("■■■  ○○○ P2│"));
+		assert_eq!(rows[6].matches("Idle").count(), 2);
+		assert!(rows[7].contains('█'));
+		assert!(rows[8].contains("▀▀▀"));
+	}
+}
@@ -0,0 +1,275 @@
+use godot::prelude::*;
+
+use crate::player::{Player, PlayerState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GodotConvert, Var, Export)]
+#[godot(via = i64)]
+pub enum Outcome {
+	Hit,
+	Block,
+	GuardBreak,
+	Ko,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interaction {
+	pub attacker_p1: bool,
+	pub outcome: Outcome,
+	pub counter_hit: bool,
+	/// Frames the attacker became actionable before the defender, negative if after.
+	/// `None` if the next interaction started before both were actionable.
+	pub advantage: Option<i16>,
+	/// Frames the defender was actionable between the previous interaction of the same attacker
+	/// and this one. 0 is a true combo, or a true block string when blocked.
+	pub gap: Option<u16>,
+	/// Hits landed in a row without a gap, including this one
+	pub combo: u8,
+}
+
+impl Interaction {
+	/// Same interaction with the players swapped.
+	#[inline]
+	pub const fn mirrored(self) -> Self {
+		Interaction {
+			attacker_p1: !self.attacker_p1,
+			..self
+		}
+	}
+
+	pub fn to_dictionary(self) -> Dictionary {
+		let mut res = Dictionary::new();
+
+		res.set("attacker_p1", self.attacker_p1);
+		res.set("outcome", self.outcome);
+		res.set("counter_hit", self.counter_hit);
+		res.set(
+			"advantage",
+			self.advantage.map_or(Variant::nil(), |x| x.to_variant()),
+		);
+		res.set("gap", self.gap.map_or(Variant::nil(), |x| x.to_variant()));
+		res.set("combo", self.combo);
+
+		res
+	}
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Tracking {
+	interaction: Interaction,
+	/// Active frames since the interaction started
+	frame: u16,
+	attacker_free: Option<u16>,
+	defender_free: Option<u16>,
+	finished: bool,
+}
+
+/// Follows every hit and block until both players are actionable again. Hitstop is not counted,
+/// only frames where the players actually move.
+#[derive(Debug, Clone, Default)]
+pub struct FrameAdvantage {
+	current: Option<Tracking>,
+	last: Option<Interaction>,
+	count: u32,
+}
+
+impl FrameAdvantage {
+	/// Frames the defender may stay actionable for the next hit to still be a follow-up
+	const GAP_LIMIT: u16 = 60;
+
+	pub const fn new() -> Self {
+		FrameAdvantage {
+			current: None,
+			last: None,
+			count: 0,
+		}
+	}
+
+	/// Tracks the same interactions with the players swapped.
+	pub fn mirrored(&self) -> Self {
+		FrameAdvantage {
+			current: self.current.map(|tracking| Tracking {
+				interaction: tracking.interaction.mirrored(),
+				..tracking
+			}),
+			last: self.last.map(Interaction::mirrored),
+			count: self.count,
+		}
+	}
+
+	/// Last finished interaction.
+	#[inline]
+	pub const fn last(&self) -> Option<Interaction> {
+		self.last
+	}
+
+	/// Number of finished interactions, changes when `last` does.
+	#[inline]
+	pub const fn count(&self) -> u32 {
+		self.count
+	}
+
+	/// Must be called after every frame the players are updated in.
+	pub fn update(&mut self, player1: &Player, player2: &Player) {
+		if let Some(mut tracking) = self.current {
+			let (attacker, defender) = match tracking.interaction.attacker_p1 {
+				true => (player1, player2),
+				false => (player2, player1),
+			};
+
+			tracking.frame += 1;
+			if tracking.attacker_free.is_none() && attacker.can_attack() {
+				tracking.attacker_free = Some(tracking.frame);
+			}
+			if tracking.defender_free.is_none() && defender.can_attack() {
+				tracking.defender_free = Some(tracking.frame);
+			}
+
+			if let (Some(att), Some(def), false) = (
+				tracking.attacker_free,
+				tracking.defender_free,
+				tracking.finished,
+			) {
+				tracking.interaction.advantage = Some(def as i16 - att as i16);
+				self.finish(&mut tracking);
+			}
+
+			let expired = tracking
+				.defender_free
+				.is_some_and(|free| tracking.frame - free > Self::GAP_LIMIT);
+			self.current = (!expired).then_some(tracking);
+		}
+
+		for (attacker_p1, defender) in [(true, player2), (false, player1)] {
+			if let Some(outcome) = Self::outcome(defender.state()) {
+				self.start(attacker_p1, outcome, defender.counter_hit);
+			}
+		}
+	}
+
+	/// Finishes the interaction being tracked, must be called on the frame the round ends.
+	pub fn end_round(&mut self) {
+		if let Some(mut tracking) = self.current.take() {
+			if !tracking.finished {
+				self.finish(&mut tracking);
+			}
+		}
+	}
+
+	fn start(&mut self, attacker_p1: bool, outcome: Outcome, counter_hit: bool) {
+		let lands = matches!(outcome, Outcome::Hit | Outcome::Ko);
+		let mut gap = None;
+		let mut combo = lands as u8;
+
+		if let Some(mut prev) = self.current.take() {
+			if prev.interaction.attacker_p1 == attacker_p1 {
+				let frames = prev.defender_free.map_or(0, |free| prev.frame - free);
+				gap = Some(frames);
+
+				if frames == 0 && lands {
+					combo = prev.interaction.combo.saturating_add(1);
+				}
+			}
+
+			if !prev.finished {
+				self.finish(&mut prev);
+			}
+		}
+
+		self.current = Some(Tracking {
+			interaction: Interaction {
+				attacker_p1,
+				outcome,
+				counter_hit,
+				advantage: None,
+				gap,
+				combo,
+			},
+			frame: 0,
+			attacker_free: None,
+			defender_free: None,
+			finished: false,
+		});
+	}
+
+	#[inline]
+	fn finish(&mut self, tracking: &mut Tracking) {
+		tracking.finished = true;
+		self.last = Some(tracking.interaction);
+		self.count += 1;
+	}
+
+	#[inline]
+	const fn outcome(state: PlayerState) -> Option<Outcome> {
+		match state {
+			PlayerState::Hit(0) => Some(Outcome::Hit),
+			PlayerState::HBlock(0, _) | PlayerState::LBlock(0, _) => {
+				Some(Outcome::Block)
+			}
+			PlayerState::GuardBreak(0) => Some(Outcome::GuardBreak),
+			PlayerState::Dead(false) => Some(Outcome::Ko),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::Outcome;
+	use crate::{
+		input::{FgInput, InputConfig},
+		simul::Match,
+		training::DummyBehaviour,
+	};
+
+	const NONE: FgInput = FgInput::new(0, 0, false, false);
+	const ATTACK: FgInput = FgInput::new(0, 0, true, false);
+
+	fn training(behaviour: DummyBehaviour) -> Match {
+		let mut sim = Match::new(InputConfig::human(), InputConfig::human());
+		sim.set_training(false, behaviour);
+		sim.set_reset_spacing(250, Match::STAGE_LEN / 2);
+		sim.reset_positions();
+		sim
+	}
+
+	#[test]
+	fn blocked_normal() {
+		let mut sim = training(DummyBehaviour::BlockAll);
+
+		sim.step(ATTACK, NONE);
+		for _ in 0..60 {
+			sim.step(NONE, NONE);
+		}
+
+		let interaction = sim.frame_advantage().unwrap().last().unwrap();
+		assert!(interaction.attacker_p1);
+		assert_eq!(interaction.outcome, Outcome::Block);
+		assert_eq!(interaction.gap, None);
+		assert_eq!(interaction.combo, 0);
+		assert!(interaction.advantage.is_some());
+	}
+
+	#[test]
+	fn cancel_combo() {
+		let mut sim = training(DummyBehaviour::Stand);
+
+		sim.step(ATTACK, NONE);
+		while sim.player_state(false) == 0 {
+			sim.step(NONE, NONE);
+		}
+		// Cancel into the special while the opponent is still in hitstun
+		sim.step(ATTACK, NONE);
+		for _ in 0..60 {
+			sim.step(NONE, NONE);
+		}
+
+		// The KO is published when the round ends
+		let advantage = sim.frame_advantage().unwrap();
+		assert_eq!(advantage.count(), 2);
+		let second = advantage.last().unwrap();
+		assert_eq!(second.outcome, Outcome::Ko);
+		assert_eq!(second.advantage, None);
+		assert_eq!(second.gap, Some(0));
+		assert_eq!(second.combo, 2);
+	}
+}
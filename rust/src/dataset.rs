@@ -0,0 +1,586 @@
+use std::{
+	collections::VecDeque,
+	fs::{self, File},
+	io::{self, BufWriter, Write},
+	mem,
+	path::Path,
+};
+
+use godot::prelude::*;
+
+use crate::{
+	input::FgInput,
+	replay::Replay,
+	simul::{self, Match},
+};
+
+/// Actions of the agent in `fighter_ai_controller.gd`, in the order of its action space. Every
+/// action is played over `MacroAction::LEN` frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MacroAction {
+	FWalk,
+	BWalk,
+	FDash,
+	BDash,
+	NAttack,
+	MAttack,
+	NHold,
+	MHold,
+	NRelease,
+	MRelease,
+	None,
+}
+
+impl MacroAction {
+	/// `action_repeat` of the agent
+	pub const LEN: usize = 5;
+	pub const COUNT: usize = 11;
+	pub const ALL: [Self; Self::COUNT] = [
+		MacroAction::FWalk,
+		MacroAction::BWalk,
+		MacroAction::FDash,
+		MacroAction::BDash,
+		MacroAction::NAttack,
+		MacroAction::MAttack,
+		MacroAction::NHold,
+		MacroAction::MHold,
+		MacroAction::NRelease,
+		MacroAction::MRelease,
+		MacroAction::None,
+	];
+
+	/// Key of the action in the agent's action space.
+	pub const fn name(self) -> &'static str {
+		match self {
+			MacroAction::FWalk => "fwalk",
+			MacroAction::BWalk => "bwalk",
+			MacroAction::FDash => "fdash",
+			MacroAction::BDash => "bdash",
+			MacroAction::NAttack => "nattack",
+			MacroAction::MAttack => "mattack",
+			MacroAction::NHold => "nhold",
+			MacroAction::MHold => "mhold",
+			MacroAction::NRelease => "nrelease",
+			MacroAction::MRelease => "mrelease",
+			MacroAction::None => "none",
+		}
+	}
+
+	/// Movement, attack press and attack hold of every frame, like `set_action` of the agent.
+	/// `holds` is whether attack was held when the action started.
+	pub fn frames(self, holds: bool) -> [(i8, bool, bool); Self::LEN] {
+		let holds = self.holds(holds);
+		let mut frames = [(0, false, holds); Self::LEN];
+
+		for (i, (movement, press, _)) in frames.iter_mut().enumerate() {
+			*movement = match self {
+				MacroAction::FWalk => 1,
+				MacroAction::FDash if i == 0 || i == 2 => 1,
+				MacroAction::BDash if i == 0 || i == 2 => -1,
+				MacroAction::BWalk
+				| MacroAction::MAttack
+				| MacroAction::MHold
+				| MacroAction::MRelease => -1,
+				_ => 0,
+			};
+			*press = i == 0
+				&& matches!(
+					self,
+					MacroAction::NAttack
+						| MacroAction::MAttack | MacroAction::NHold
+						| MacroAction::MHold
+				);
+		}
+
+		frames
+	}
+
+	/// Whether attack is held after the action.
+	#[inline]
+	pub const fn holds(self, holds: bool) -> bool {
+		match self {
+			MacroAction::NHold | MacroAction::MHold => true,
+			MacroAction::NRelease | MacroAction::MRelease => false,
+			_ => holds,
+		}
+	}
+
+	/// The action closest to what a human did over `inputs`, at most `LEN` frames. Dashes are
+	/// only recognised when both taps fall in the same action.
+	pub fn from_inputs(inputs: &[FgInput], holds: bool) -> Self {
+		let Some(last) = inputs.last() else {
+			return MacroAction::None;
+		};
+
+		if let Some(press) = inputs.iter().find(|input| input.attack_press) {
+			return match (last.attack_hold, press.movement != 0) {
+				(true, false) => MacroAction::NHold,
+				(true, true) => MacroAction::MHold,
+				(false, false) => MacroAction::NAttack,
+				(false, true) => MacroAction::MAttack,
+			};
+		}
+
+		if let Some(release) = inputs
+			.iter()
+			.find(|input| !input.attack_hold)
+			.filter(|_| holds)
+		{
+			return match release.movement {
+				0 => MacroAction::NRelease,
+				_ => MacroAction::MRelease,
+			};
+		}
+
+		// Tapping twice, the first tap may have been held from before
+		let taps = |direction| {
+			(inputs[0].movement == direction) as usize
+				+ inputs.windows(2)
+					.filter(|pair| {
+						pair[0].movement != direction
+							&& pair[1].movement == direction
+					})
+					.count()
+		};
+		if taps(1) >= 2 {
+			return MacroAction::FDash;
+		}
+		if taps(-1) >= 2 {
+			return MacroAction::BDash;
+		}
+
+		match inputs
+			.iter()
+			.map(|input| input.movement as i32)
+			.sum::<i32>()
+		{
+			1.. => MacroAction::FWalk,
+			..0 => MacroAction::BWalk,
+			0 => MacroAction::None,
+		}
+	}
+}
+
+/// Observations exactly as `get_obs` of `fighter_ai_controller.gd` builds them: player
+/// observations from 12 to 16 frames ago, whether attack is held, the last action one-hot and
+/// the punish observation from 7 frames ago.
+#[derive(Debug, Clone)]
+pub struct AgentObservation {
+	p1: bool,
+	memory: VecDeque<[f32; Match::PLAYER_OBS_LEN]>,
+	prev: VecDeque<[f32; Match::PLAYER_OBS_LEN]>,
+	punish_prev: VecDeque<[f32; Match::PUNISH_OBS_LEN]>,
+	holds: bool,
+	last_action: Option<MacroAction>,
+}
+
+impl AgentObservation {
+	const MEMORY_LEN: usize = 5;
+	const PREV_LEN: usize = 12;
+	/// Length of every observation
+	pub const SIZE: usize = Match::PLAYER_OBS_LEN * Self::MEMORY_LEN
+		+ 1 + MacroAction::COUNT
+		+ Match::PUNISH_OBS_LEN;
+
+	/// Observations are kept in fixed size arrays, so once the queues are full no frame
+	/// allocates.
+	pub fn new(p1: bool) -> Self {
+		AgentObservation {
+			p1,
+			memory: VecDeque::with_capacity(Self::MEMORY_LEN + 1),
+			prev: VecDeque::with_capacity(Self::PREV_LEN + 1),
+			punish_prev: VecDeque::with_capacity(Self::PREV_LEN + 1),
+			holds: false,
+			last_action: None,
+		}
+	}
+
+	#[inline]
+	pub const fn holds(&self) -> bool {
+		self.holds
+	}
+
+	/// Must be called after every frame.
+	pub fn frame(&mut self, sim: &Match) {
+		let obs = self.player_obs(sim);
+		self.fill_memory(obs);
+
+		let mut punish = [0.0; Match::PUNISH_OBS_LEN];
+		sim.punish_obs_into(self.p1, &mut punish);
+
+		self.prev.push_back(obs);
+		self.punish_prev.push_back(punish);
+		if self.prev.len() > Self::PREV_LEN {
+			self.memory.extend(self.prev.pop_front());
+			self.punish_prev.pop_front();
+		}
+		if self.memory.len() > Self::MEMORY_LEN {
+			self.memory.pop_front();
+		}
+	}
+
+	pub fn observe(&mut self, sim: &Match) -> Vec<f32> {
+		let mut res = vec![0.0; Self::SIZE];
+		self.observe_into(sim, &mut res);
+
+		res
+	}
+
+	/// Writes `observe` into the start of `out`, without allocating.
+	///
+	/// Panics if `out` is shorter than `SIZE`.
+	pub fn observe_into(&mut self, sim: &Match, out: &mut [f32]) {
+		self.fill_memory(self.player_obs(sim));
+
+		let (memory, rest) =
+			out[..Self::SIZE].split_at_mut(Match::PLAYER_OBS_LEN * Self::MEMORY_LEN);
+		for (out, obs) in memory
+			.chunks_exact_mut(Match::PLAYER_OBS_LEN)
+			.zip(&self.memory)
+		{
+			out.copy_from_slice(obs);
+		}
+
+		let (holds, rest) = rest.split_at_mut(1);
+		holds[0] = self.holds as i32 as f32;
+
+		let (last_action, punish) = rest.split_at_mut(MacroAction::COUNT);
+		for (out, action) in last_action.iter_mut().zip(MacroAction::ALL) {
+			*out = (self.last_action == Some(action)) as i32 as f32;
+		}
+
+		match self.punish_prev.get(Self::MEMORY_LEN - 1) {
+			Some(prev) => punish.copy_from_slice(prev),
+			None => punish.fill(0.0),
+		}
+	}
+
+	pub const fn set_action(&mut self, action: MacroAction) {
+		self.holds = action.holds(self.holds);
+		self.last_action = Some(action);
+	}
+
+	/// Round reset, the last action is kept like the agent does.
+	pub fn reset(&mut self) {
+		self.memory.clear();
+		self.prev.clear();
+		self.punish_prev.clear();
+		self.holds = false;
+	}
+
+	#[inline]
+	fn player_obs(&self, sim: &Match) -> [f32; Match::PLAYER_OBS_LEN] {
+		let mut res = [0.0; Match::PLAYER_OBS_LEN];
+		sim.player_obs_into(self.p1, &mut res);
+
+		res
+	}
+
+	fn fill_memory(&mut self, obs: [f32; Match::PLAYER_OBS_LEN]) {
+		while self.memory.len() < Self::MEMORY_LEN {
+			self.memory.push_back(obs);
+		}
+	}
+}
+
+/// (observation, action, next observation, reward, done) transitions of recorded matches, with
+/// the observations, macro actions and rewards of the agent, for behaviour cloning. Transitions
+/// are stored in columns and written as NPY files.
+#[derive(Debug, Clone, Default, GodotClass)]
+#[class(no_init)]
+pub struct BcDataset {
+	obs_size: usize,
+	obs: Vec<f32>,
+	actions: Vec<u8>,
+	next_obs: Vec<f32>,
+	rewards: Vec<f32>,
+	/// The match ended, like `done` of the agent
+	dones: Vec<bool>,
+}
+
+#[godot_api]
+impl BcDataset {
+	#[func]
+	pub fn gd_new() -> Gd<Self> {
+		Gd::from_object(Self::new())
+	}
+
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	#[func]
+	pub fn gd_add_replay(&mut self, replay: Gd<Replay>, p1: bool) -> i64 {
+		self.add_replay(&replay.bind(), p1) as i64
+	}
+
+	/// Adds the transitions of the player, returns how many. Actions are split at every round
+	/// end and the last unfinished action of the replay is left out.
+	pub fn add_replay(&mut self, replay: &Replay, p1: bool) -> usize {
+		let mut sim = replay.start();
+		let mut agent = AgentObservation::new(p1);
+		// Swapped every action, so the frames do not allocate
+		let mut obs = vec![0.0; AgentObservation::SIZE];
+		let mut next_obs = vec![0.0; AgentObservation::SIZE];
+		agent.observe_into(&sim, &mut obs);
+		self.obs_size = AgentObservation::SIZE;
+
+		let len = self.actions.len();
+		let mut window = Vec::with_capacity(MacroAction::LEN);
+		let mut reward = 0.0;
+
+		for &[input1, input2] in &replay.inputs {
+			let res = sim.step(input1, input2);
+			agent.frame(&sim);
+			window.push(if p1 { input1 } else { input2 });
+			reward += sim.ai_reward(res, p1);
+
+			let round_over =
+				!matches!(res, simul::Result::Continue | simul::Result::Pause);
+			if window.len() < MacroAction::LEN && !round_over {
+				continue;
+			}
+
+			let action = MacroAction::from_inputs(&window, agent.holds());
+			agent.set_action(action);
+			agent.observe_into(&sim, &mut next_obs);
+
+			if round_over {
+				sim.new_round();
+			}
+			let done = round_over && !sim.continues();
+			self.push(&obs, action, &next_obs, reward, done);
+
+			if done {
+				break;
+			}
+
+			window.clear();
+			reward = 0.0;
+			match round_over {
+				true => {
+					agent.reset();
+					agent.observe_into(&sim, &mut obs);
+				}
+				false => mem::swap(&mut obs, &mut next_obs),
+			}
+		}
+
+		self.actions.len() - len
+	}
+
+	#[func]
+	pub fn len(&self) -> i64 {
+		self.actions.len() as i64
+	}
+
+	#[func]
+	pub fn is_empty(&self) -> bool {
+		self.actions.is_empty()
+	}
+
+	/// 0 until a replay is added.
+	#[func]
+	pub fn obs_size(&self) -> i64 {
+		self.obs_size as i64
+	}
+
+	/// Writes `obs.npy`, `action.npy`, `next_obs.npy`, `reward.npy` and `done.npy` into `dir`,
+	/// creating it if needed. Actions are indices into the agent's action space.
+	#[func]
+	pub fn write_npy(&self, dir: GString) -> bool {
+		self.write(Path::new(&dir.to_string())).is_ok()
+	}
+
+	pub fn write(&self, dir: &Path) -> io::Result<()> {
+		fs::create_dir_all(dir)?;
+
+		let len = self.actions.len();
+		let obs_shape = [len, self.obs_size];
+		let floats = |data: &[f32]| {
+			data.iter()
+				.flat_map(|x| x.to_le_bytes())
+				.collect::<Vec<_>>()
+		};
+		let bools = self.dones.iter().map(|&x| x as u8).collect::<Vec<_>>();
+
+		write_npy(&dir.join("obs.npy"), "<f4", &obs_shape, &floats(&self.obs))?;
+		write_npy(&dir.join("action.npy"), "|u1", &[len], &self.actions)?;
+		write_npy(
+			&dir.join("next_obs.npy"),
+			"<f4",
+			&obs_shape,
+			&floats(&self.next_obs),
+		)?;
+		write_npy(
+			&dir.join("reward.npy"),
+			"<f4",
+			&[len],
+			&floats(&self.rewards),
+		)?;
+		write_npy(&dir.join("done.npy"), "|b1", &[len], &bools)
+	}
+
+	fn push(
+		&mut self,
+		obs: &[f32],
+		action: MacroAction,
+		next_obs: &[f32],
+		reward: f32,
+		done: bool,
+	) {
+		self.obs.extend_from_slice(obs);
+		self.actions.push(action as u8);
+		self.next_obs.extend_from_slice(next_obs);
+		self.rewards.push(reward);
+		self.dones.push(done);
+	}
+}
+
+/// NPY format version 1.0, `data` must already be in the byte order of `descr`.
+fn write_npy(path: &Path, descr: &str, shape: &[usize], data: &[u8]) -> io::Result<()> {
+	let shape = match shape {
+		[len] => format!("({len},)"),
+		_ => format!(
+			"({})",
+			shape.iter()
+				.map(usize::to_string)
+				.collect::<Vec<_>>()
+				.join(", ")
+		),
+	};
+	let mut header =
+		format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape}, }}");
+
+	// Magic, version and header length take 10 bytes, the data starts 64 byte aligned
+	let padding = (64 - (10 + header.len() + 1) % 64) % 64;
+	header.extend(std::iter::repeat_n(' ', padding));
+	header.push('\n');
+
+	let mut file = BufWriter::new(File::create(path)?);
+	file.write_all(b"\x93NUMPY\x01\x00")?;
+	file.write_all(&(header.len() as u16).to_le_bytes())?;
+	file.write_all(header.as_bytes())?;
+	file.write_all(data)?;
+	file.flush()
+}
+
+#[cfg(test)]
+mod test {
+	use std::time::{SystemTime, UNIX_EPOCH};
+
+	use super::{AgentObservation, BcDataset, MacroAction};
+	use crate::{
+		decoder::{InputDecoder, SocdPolicy},
+		input::{FgInput, InputConfig},
+		replay::Replay,
+		simul,
+	};
+
+	/// Inputs of the agent playing `actions`, decoded like `battle_scene.gd` does.
+	fn agent_inputs(actions: &[MacroAction]) -> Vec<FgInput> {
+		let mut decoder = InputDecoder::new(SocdPolicy::Neutral);
+		let mut holds = false;
+
+		actions.iter()
+			.flat_map(|&action| {
+				let frames = action.frames(holds);
+				holds = action.holds(holds);
+				frames
+			})
+			.map(|(movement, press, hold)| {
+				decoder.decode_movement(movement, press, hold)
+			})
+			.collect()
+	}
+
+	/// Releasing needs a hold, and the agent's attacks while holding look like holds.
+	fn reachable(action: MacroAction, holds: bool) -> bool {
+		match action {
+			MacroAction::NRelease | MacroAction::MRelease => holds,
+			MacroAction::NAttack | MacroAction::MAttack => !holds,
+			_ => true,
+		}
+	}
+
+	#[test]
+	fn from_inputs() {
+		for holds in [false, true] {
+			for action in MacroAction::ALL
+				.into_iter()
+				.filter(|&a| reachable(a, holds))
+			{
+				let inputs = match holds {
+					// Attack held from before the action
+					true => agent_inputs(&[MacroAction::NHold, action])
+						[MacroAction::LEN..]
+						.to_vec(),
+					false => agent_inputs(&[action]),
+				};
+				assert_eq!(MacroAction::from_inputs(&inputs, holds), action);
+			}
+		}
+	}
+
+	#[test]
+	fn export() {
+		let mut holds = false;
+		let actions: Vec<MacroAction> = (0..1000)
+			.map(|i| MacroAction::ALL[(i * 7) % MacroAction::COUNT])
+			.filter(|&action| {
+				let reachable = reachable(action, holds);
+				if reachable {
+					holds = action.holds(holds);
+				}
+				reachable
+			})
+			.collect();
+
+		let mut replay = Replay::new(InputConfig::bot(), InputConfig::bot());
+		let none = FgInput::new(0, 0, false, false);
+		for input in agent_inputs(&actions) {
+			replay.push(input, none);
+		}
+
+		let mut round_end = None;
+		let mut frame = 0;
+		replay.play(|_, res| {
+			frame += 1;
+			if !matches!(res, simul::Result::Continue | simul::Result::Pause) {
+				round_end.get_or_insert(frame);
+			}
+		});
+		let round_end = round_end.unwrap();
+
+		let mut dataset = BcDataset::new();
+		let len = dataset.add_replay(&replay, true);
+		assert!(len > round_end / MacroAction::LEN);
+		assert_eq!(dataset.obs_size, AgentObservation::SIZE);
+		assert_eq!(dataset.obs.len(), len * dataset.obs_size);
+
+		// Actions line up until the first round ends
+		let recovered = round_end / MacroAction::LEN;
+		assert_eq!(
+			dataset.actions[..recovered],
+			actions[..recovered]
+				.iter()
+				.map(|&a| a as u8)
+				.collect::<Vec<_>>()
+		);
+
+		// Unique per run, so parallel runs do not write over each other
+		let nanos = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap()
+			.as_nanos();
+		let dir = std::env::temp_dir()
+			.join(format!("botsies_bc_export_{}_{nanos}", std::process::id()));
+		dataset.write(&dir).unwrap();
+		let action = std::fs::read(dir.join("action.npy")).unwrap();
+		assert_eq!(&action[..8], b"\x93NUMPY\x01\x00");
+		let header_len = u16::from_le_bytes([action[8], action[9]]) as usize;
+		assert_eq!((10 + header_len) % 64, 0);
+		assert_eq!(action.len(), 10 + header_len + len);
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+}
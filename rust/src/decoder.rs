@@ -0,0 +1,188 @@
+use godot::prelude::*;
+
+use crate::input::FgInput;
+
+/// State of the buttons this frame. Directions are relative to where the player faces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RawInput {
+	pub forward: bool,
+	pub backward: bool,
+	pub attack: bool,
+}
+
+impl RawInput {
+	pub const fn new(forward: bool, backward: bool, attack: bool) -> Self {
+		RawInput {
+			forward,
+			backward,
+			attack,
+		}
+	}
+}
+
+/// What happens when forward and backward are held at the same time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GodotConvert, Var, Export)]
+#[godot(via = i64)]
+pub enum SocdPolicy {
+	/// No movement, same as `forward - backward`
+	Neutral,
+	/// The direction pressed last wins
+	LastInput,
+	Forward,
+	Backward,
+}
+
+/// Turns raw button states into `FgInput`s, keeping the previous frame to find the presses.
+/// Every frontend should use one per player instead of tracking the edges itself.
+#[derive(Debug, Clone, GodotClass)]
+#[class(no_init)]
+pub struct InputDecoder {
+	pub policy: SocdPolicy,
+	prev: RawInput,
+	prev_movement: i8,
+	/// Direction pressed last, for `SocdPolicy::LastInput`
+	last_direction: i8,
+}
+
+#[godot_api]
+impl InputDecoder {
+	#[func]
+	pub fn gd_new(policy: SocdPolicy) -> Gd<Self> {
+		Gd::from_object(Self::new(policy))
+	}
+
+	pub const fn new(policy: SocdPolicy) -> Self {
+		InputDecoder {
+			policy,
+			prev: RawInput::new(false, false, false),
+			prev_movement: 0,
+			last_direction: 0,
+		}
+	}
+
+	#[func]
+	pub fn gd_decode(&mut self, forward: bool, backward: bool, attack: bool) -> Gd<FgInput> {
+		Gd::from_object(self.decode(RawInput::new(forward, backward, attack)))
+	}
+
+	/// For frontends that decide attack presses and holds themselves, like the macro actions
+	/// of `fighter_ai_controller.gd`. Only the movement is decoded.
+	#[func]
+	pub fn gd_decode_movement(
+		&mut self,
+		movement: i8,
+		attack_press: bool,
+		attack_hold: bool,
+	) -> Gd<FgInput> {
+		Gd::from_object(self.decode_movement(movement, attack_press, attack_hold))
+	}
+
+	#[func]
+	pub fn set_policy(&mut self, policy: SocdPolicy) {
+		self.policy = policy;
+	}
+
+	/// Forgets the previous frame, so held buttons count as pressed again.
+	#[func]
+	pub fn reset(&mut self) {
+		*self = Self::new(self.policy);
+	}
+
+	pub fn decode_movement(
+		&mut self,
+		movement: i8,
+		attack_press: bool,
+		attack_hold: bool,
+	) -> FgInput {
+		let raw = RawInput::new(movement > 0, movement < 0, attack_hold);
+		let (movement, movement_press) = self.movement(raw);
+		self.prev = raw;
+
+		FgInput::new(movement, movement_press, attack_press, attack_hold)
+	}
+
+	pub fn decode(&mut self, raw: RawInput) -> FgInput {
+		let (movement, movement_press) = self.movement(raw);
+		let attack_press = raw.attack && !self.prev.attack;
+		self.prev = raw;
+
+		FgInput::new(movement, movement_press, attack_press, raw.attack)
+	}
+
+	/// Movement and movement press, the press is the new movement whenever it changes.
+	fn movement(&mut self, raw: RawInput) -> (i8, i8) {
+		let forward_press = raw.forward && !self.prev.forward;
+		let backward_press = raw.backward && !self.prev.backward;
+
+		// Pressing both on the same frame keeps the old direction
+		match (forward_press, backward_press) {
+			(true, false) => self.last_direction = 1,
+			(false, true) => self.last_direction = -1,
+			_ => {}
+		}
+
+		let movement = match (raw.forward, raw.backward) {
+			(true, false) => 1,
+			(false, true) => -1,
+			(false, false) => 0,
+			(true, true) => match self.policy {
+				SocdPolicy::Neutral => 0,
+				SocdPolicy::LastInput => self.last_direction,
+				SocdPolicy::Forward => 1,
+				SocdPolicy::Backward => -1,
+			},
+		};
+
+		let movement_press = match movement != self.prev_movement {
+			true => movement,
+			false => 0,
+		};
+		self.prev_movement = movement;
+
+		(movement, movement_press)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{InputDecoder, RawInput, SocdPolicy};
+	use crate::input::FgInput;
+
+	const NONE: RawInput = RawInput::new(false, false, false);
+	const FORWARD: RawInput = RawInput::new(true, false, false);
+	const BACKWARD: RawInput = RawInput::new(false, true, false);
+	const BOTH: RawInput = RawInput::new(true, true, false);
+	const ATTACK: RawInput = RawInput::new(false, false, true);
+
+	#[test]
+	fn edges() {
+		let mut decoder = InputDecoder::new(SocdPolicy::Neutral);
+
+		assert_eq!(decoder.decode(FORWARD), FgInput::new(1, 1, false, false));
+		assert_eq!(decoder.decode(FORWARD), FgInput::new(1, 0, false, false));
+		assert_eq!(decoder.decode(NONE), FgInput::new(0, 0, false, false));
+		assert_eq!(decoder.decode(FORWARD), FgInput::new(1, 1, false, false));
+
+		assert_eq!(decoder.decode(ATTACK), FgInput::new(0, 0, true, true));
+		assert_eq!(decoder.decode(ATTACK), FgInput::new(0, 0, false, true));
+		assert_eq!(decoder.decode(NONE), FgInput::new(0, 0, false, false));
+
+		decoder.decode(ATTACK);
+		decoder.reset();
+		assert_eq!(decoder.decode(ATTACK), FgInput::new(0, 0, true, true));
+	}
+
+	#[test]
+	fn socd() {
+		let movements = |policy| {
+			let mut decoder = InputDecoder::new(policy);
+			[FORWARD, BOTH, BACKWARD, BOTH, NONE, BOTH]
+				.map(|raw| decoder.decode(raw).movement)
+		};
+
+		assert_eq!(movements(SocdPolicy::Neutral), [1, 0, -1, 0, 0, 0]);
+		assert_eq!(movements(SocdPolicy::LastInput), [1, -1, -1, 1, 0, 1]);
+		assert_eq!(movements(SocdPolicy::Forward), [1, 1, -1, 1, 0, 1]);
+		assert_eq!(movements(SocdPolicy::Backward), [1, -1, -1, -1, 0, -1]);
+	}
+}
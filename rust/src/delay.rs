@@ -0,0 +1,121 @@
+use std::collections::VecDeque;
+
+use crate::{input::FgInput, rng::Rng};
+
+/// Holds inputs back before they reach the player, like online latency. With jitter the delay of
+/// every input is random, but inputs never overtake each other: inputs arriving on the same frame
+/// are merged, and frames without a new input repeat the last one without its presses.
+#[derive(Debug, Clone, Hash)]
+pub struct InputDelay {
+	frames: u8,
+	jitter: u8,
+	/// Inputs and the frame they arrive on
+	queue: VecDeque<(u32, FgInput)>,
+	frame: u32,
+	last: FgInput,
+}
+
+impl InputDelay {
+	/// Every input is delayed by `frames` plus up to `jitter` frames.
+	pub const fn new(frames: u8, jitter: u8) -> Self {
+		InputDelay {
+			frames,
+			jitter,
+			queue: VecDeque::new(),
+			frame: 0,
+			last: FgInput::new(0, 0, false, false),
+		}
+	}
+
+	#[inline]
+	pub const fn frames(&self) -> u8 {
+		self.frames
+	}
+
+	#[inline]
+	pub const fn jitter(&self) -> u8 {
+		self.jitter
+	}
+
+	/// Takes this frame's input, returns the input arriving this frame.
+	pub fn delay(&mut self, rng: &mut Rng, input: FgInput) -> FgInput {
+		let jitter = rng.range(0, self.jitter as i64) as u32;
+		let arrival = self.frame + self.frames as u32 + jitter;
+		let arrival = self
+			.queue
+			.back()
+			.map_or(arrival, |&(last, _)| arrival.max(last));
+		self.queue.push_back((arrival, input));
+
+		let mut res = FgInput {
+			movement_press: 0,
+			attack_press: false,
+			..self.last
+		};
+		while let Some(&(_, input)) = self
+			.queue
+			.front()
+			.filter(|&&(arrival, _)| arrival <= self.frame)
+		{
+			self.queue.pop_front();
+
+			res = FgInput {
+				movement_press: match input.movement_press {
+					0 => res.movement_press,
+					press => press,
+				},
+				attack_press: res.attack_press || input.attack_press,
+				..input
+			};
+		}
+
+		self.frame += 1;
+		self.last = res;
+
+		res
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::InputDelay;
+	use crate::{input::FgInput, rng::Rng};
+
+	const NONE: FgInput = FgInput::new(0, 0, false, false);
+	const ATTACK: FgInput = FgInput::new(0, 0, true, true);
+	const HOLD: FgInput = FgInput::new(0, 0, false, true);
+
+	#[test]
+	fn fixed() {
+		let mut rng = Rng::new(0);
+		let mut delay = InputDelay::new(3, 0);
+
+		assert_eq!(delay.delay(&mut rng, ATTACK), NONE);
+		assert_eq!(delay.delay(&mut rng, HOLD), NONE);
+		assert_eq!(delay.delay(&mut rng, NONE), NONE);
+		assert_eq!(delay.delay(&mut rng, NONE), ATTACK);
+		assert_eq!(delay.delay(&mut rng, NONE), HOLD);
+		assert_eq!(delay.delay(&mut rng, NONE), NONE);
+
+		let mut delay = InputDelay::new(0, 0);
+		assert_eq!(delay.delay(&mut rng, ATTACK), ATTACK);
+	}
+
+	#[test]
+	fn jitter() {
+		let mut rng = Rng::new(0);
+		let mut delay = InputDelay::new(2, 4);
+		let mut presses = 0;
+
+		for i in 0..1000 {
+			let input = match i % 10 {
+				0 => ATTACK,
+				_ => NONE,
+			};
+			presses += delay.delay(&mut rng, input).attack_press as u32;
+		}
+
+		// No press is lost, the last one may still be on the way
+		assert!((99..=100).contains(&presses));
+	}
+}
@@ -0,0 +1,181 @@
+use std::hash::{Hash, Hasher};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{rng::Rng, simul::Match};
+
+/// Parameters of a round, rolled by `DomainRandomization`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoundParams {
+	pub spacing: i16,
+	pub timer: u16,
+	/// Walking speed in percent, for each player
+	pub walk_speeds: Option<[u8; 2]>,
+	/// Input delay in frames, for each player
+	pub reaction_delays: Option<[u8; 2]>,
+}
+
+/// Randomises every round so agents do not overfit to the fixed opening. All ranges are
+/// inclusive. Walk speeds and reaction delays are left alone unless they have a range.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DomainRandomization {
+	pub spacing: (i16, i16),
+	pub timer: (u16, u16),
+	pub walk_speed: Option<(u8, u8)>,
+	pub reaction_delay: Option<(u8, u8)>,
+	/// Chance of an input being lost every frame
+	pub drop_chance: f32,
+}
+
+impl DomainRandomization {
+	pub const fn new(spacing: i16, timer: u16) -> Self {
+		DomainRandomization {
+			spacing: (spacing, spacing),
+			timer: (timer, timer),
+			walk_speed: None,
+			reaction_delay: None,
+			drop_chance: 0.0,
+		}
+	}
+
+	/// Returns false and keeps the old range if either end is off the stage.
+	pub fn set_spacing(&mut self, min: i16, max: i16) -> bool {
+		let valid = [min, max]
+			.iter()
+			.all(|spacing| (0..=Match::STAGE_LEN).contains(spacing));
+		if valid {
+			self.spacing = (min, max);
+		}
+
+		valid
+	}
+
+	pub fn roll(&self, rng: &mut Rng) -> RoundParams {
+		let spacing = Self::range(rng, self.spacing.0 as i64, self.spacing.1 as i64) as i16;
+		let timer = Self::range(rng, self.timer.0 as i64, self.timer.1 as i64) as u16;
+
+		let mut per_player = |range: Option<(u8, u8)>| {
+			range.map(|(min, max)| {
+				[(); 2].map(|_| Self::range(rng, min as i64, max as i64) as u8)
+			})
+		};
+		let walk_speeds = per_player(self.walk_speed);
+		let reaction_delays = per_player(self.reaction_delay);
+
+		RoundParams {
+			spacing,
+			timer,
+			walk_speeds,
+			reaction_delays,
+		}
+	}
+
+	#[inline]
+	pub fn drops_input(&self, rng: &mut Rng) -> bool {
+		self.drop_chance > 0.0 && rng.chance(self.drop_chance)
+	}
+
+	#[inline]
+	fn range(rng: &mut Rng, min: i64, max: i64) -> i64 {
+		rng.range(min.min(max), min.max(max))
+	}
+}
+
+impl Hash for DomainRandomization {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		(
+			self.spacing,
+			self.timer,
+			self.walk_speed,
+			self.reaction_delay,
+			self.drop_chance.to_bits(),
+		)
+			.hash(state);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::DomainRandomization;
+	use crate::{
+		input::{FgInput, InputConfig},
+		rng::Rng,
+		simul::Match,
+	};
+
+	#[test]
+	fn roll() {
+		let mut rng = Rng::new(0);
+		let mut domain = DomainRandomization::new(500, 600);
+		let params = domain.roll(&mut rng);
+		assert_eq!(params.spacing, 500);
+		assert_eq!(params.timer, 600);
+		assert_eq!(params.walk_speeds, None);
+		assert_eq!(params.reaction_delays, None);
+
+		assert!(!domain.set_spacing(-10, 800));
+		assert!(!domain.set_spacing(200, Match::STAGE_LEN + 1));
+		assert!(domain.set_spacing(200, 800));
+		domain.walk_speed = Some((80, 120));
+		domain.reaction_delay = Some((5, 0));
+		for _ in 0..100 {
+			let params = domain.roll(&mut rng);
+			assert!((200..=800).contains(&params.spacing));
+			assert!(params
+				.walk_speeds
+				.unwrap()
+				.iter()
+				.all(|x| (80..=120).contains(x)));
+			assert!(params.reaction_delays.unwrap().iter().all(|&x| x <= 5));
+		}
+	}
+
+	#[test]
+	fn new_round() {
+		let mut sim = Match::new(InputConfig::human(), InputConfig::human());
+		sim.set_seed(1);
+		sim.enable_domain_randomization();
+		sim.set_random_spacing(200, 300);
+		sim.set_random_walk_speed(50, 50);
+		sim.set_input_drop_chance(1.0);
+		sim.new_round();
+
+		assert!((200..=300).contains(&(sim.p2_pos() - sim.p1_pos())));
+		assert_eq!(sim.walk_speed(true), 50);
+		assert_eq!(sim.player(true).handicap().walk_speed, 100);
+
+		// Every input is dropped
+		sim.step(
+			FgInput::new(1, 1, true, true),
+			FgInput::new(0, 0, false, false),
+		);
+		let last = sim.player(true).history.iter().next().unwrap();
+		assert_eq!(last.input, FgInput::new(0, 0, false, false));
+
+		sim.disable_domain_randomization();
+		assert_eq!(sim.walk_speed(true), 100);
+	}
+
+	#[test]
+	fn reaction_delay() {
+		const NONE: FgInput = FgInput::new(0, 0, false, false);
+		const ATTACK: FgInput = FgInput::new(0, 0, true, true);
+
+		let mut sim = Match::new(InputConfig::human(), InputConfig::human());
+		sim.set_input_delay(true, 4, 0);
+		sim.enable_domain_randomization();
+		sim.set_random_reaction_delay(0, 0);
+		sim.new_round();
+
+		// The rolled delay is used for the round, the set one is kept
+		sim.step(ATTACK, NONE);
+		let last = sim.player(true).history.iter().next().unwrap();
+		assert_eq!(last.input, ATTACK);
+		assert_eq!(sim.input_delay(true), Some((4, 0)));
+
+		sim.disable_domain_randomization();
+		sim.step(ATTACK, NONE);
+		let last = sim.player(true).history.iter().next().unwrap();
+		assert_ne!(last.input, ATTACK);
+	}
+}
@@ -1,5 +1,7 @@
 use std::ops::{self};
 
+use godot::builtin::{Rect2, Vector2};
+
 #[derive(Clone, Copy)]
 pub struct CBox {
 	pub offsetx: i16,
@@ -65,6 +67,22 @@ impl CBox {
 		true
 	}
 
+	/// Places the box in the stage, `flip` for player 2 who faces left.
+	#[inline]
+	pub fn world(self, position: i16, flip: bool) -> WorldBox {
+		let cbox = if flip { -self } else { self };
+
+		let (x1, x2) = (cbox.offsetx + position, cbox.offsetx + cbox.x + position);
+		let (y1, y2) = (cbox.offsety, cbox.offsety + cbox.y);
+
+		WorldBox {
+			x1: x1.min(x2),
+			y1: y1.min(y2),
+			x2: x1.max(x2),
+			y2: y1.max(y2),
+		}
+	}
+
 	#[inline]
 	pub fn overlap_amount(&self, offsetx1: i16, other: CBox, offsetx2: i16) -> i16 {
 		((self.x + self.offsetx + offsetx1) - (other.x + other.offsetx + offsetx2)) / 2
@@ -95,6 +113,24 @@ impl ops::Neg for CBox {
 	}
 }
 
+/// Box in stage coordinates, x grows towards player 2's side and y grows upwards from the floor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorldBox {
+	pub x1: i16,
+	pub y1: i16,
+	pub x2: i16,
+	pub y2: i16,
+}
+
+impl From<WorldBox> for Rect2 {
+	fn from(value: WorldBox) -> Self {
+		Rect2::new(
+			Vector2::new(value.x1 as f32, value.y1 as f32),
+			Vector2::new((value.x2 - value.x1) as f32, (value.y2 - value.y1) as f32),
+		)
+	}
+}
+
 #[derive(Clone)]
 pub struct FrameData {
 	pub speed: i16,
@@ -158,18 +194,11 @@ pub const IDLE_DATA: [MoveData; 5] = [
 	},
 ];
 
-pub fn idle_data(frame: u8) -> Option<&'static MoveData> {
-	let mut frame = (frame + 1) as usize;
-
-	for d in IDLE_DATA.iter() {
-		frame = frame.saturating_sub(d.duration as usize);
+static IDLE_FRAMES: [&MoveData; move_length(&IDLE_DATA) as usize] =
+	frame_table(&IDLE_DATA);
 
-		if frame == 0 {
-			return Some(d);
-		}
-	}
-
-	None
+pub fn idle_data(frame: u8) -> Option<&'static MoveData> {
+	IDLE_FRAMES.get(frame as usize).copied()
 }
 
 pub const FWALK_DATA: [MoveData; 6] = [
@@ -223,18 +252,11 @@ pub const FWALK_DATA: [MoveData; 6] = [
 	},
 ];
 
-pub fn fwalk_data(frame: u8) -> Option<&'static MoveData> {
-	let mut frame = (frame + 1) as usize;
-
-	for d in FWALK_DATA.iter() {
-		frame = frame.saturating_sub(d.duration as usize);
-
-		if frame == 0 {
-			return Some(d);
-		}
-	}
+static FWALK_FRAMES: [&MoveData; move_length(&FWALK_DATA) as usize] =
+	frame_table(&FWALK_DATA);
 
-	None
+pub fn fwalk_data(frame: u8) -> Option<&'static MoveData> {
+	FWALK_FRAMES.get(frame as usize).copied()
 }
 
 pub const BWALK_DATA: [MoveData; 6] = [
@@ -288,18 +310,11 @@ pub const BWALK_DATA: [MoveData; 6] = [
 	},
 ];
 
-pub fn bwalk_data(frame: u8) -> Option<&'static MoveData> {
-	let mut frame = (frame + 1) as usize;
-
-	for d in BWALK_DATA.iter() {
-		frame = frame.saturating_sub(d.duration as usize);
-
-		if frame == 0 {
-			return Some(d);
-		}
-	}
+static BWALK_FRAMES: [&MoveData; move_length(&BWALK_DATA) as usize] =
+	frame_table(&BWALK_DATA);
 
-	None
+pub fn bwalk_data(frame: u8) -> Option<&'static MoveData> {
+	BWALK_FRAMES.get(frame as usize).copied()
 }
 
 pub const NNORMAL_DATA: [MoveData; 6] = [
@@ -383,18 +398,11 @@ pub const NNORMAL_DATA: [MoveData; 6] = [
 	},
 ];
 
-pub fn nnormal_data(frame: u8) -> Option<&'static MoveData> {
-	let mut frame = (frame + 1) as usize;
-
-	for d in NNORMAL_DATA.iter() {
-		frame = frame.saturating_sub(d.duration as usize);
-
-		if frame == 0 {
-			return Some(d);
-		}
-	}
+static NNORMAL_FRAMES: [&MoveData; move_length(&NNORMAL_DATA) as usize] =
+	frame_table(&NNORMAL_DATA);
 
-	None
+pub fn nnormal_data(frame: u8) -> Option<&'static MoveData> {
+	NNORMAL_FRAMES.get(frame as usize).copied()
 }
 
 pub const MNORMAL_DATA: [MoveData; 6] = [
@@ -477,18 +485,11 @@ pub const MNORMAL_DATA: [MoveData; 6] = [
 	},
 ];
 
-pub fn mnormal_data(frame: u8) -> Option<&'static MoveData> {
-	let mut frame = (frame + 1) as usize;
+static MNORMAL_FRAMES: [&MoveData; move_length(&MNORMAL_DATA) as usize] =
+	frame_table(&MNORMAL_DATA);
 
-	for d in MNORMAL_DATA.iter() {
-		frame = frame.saturating_sub(d.duration as usize);
-
-		if frame == 0 {
-			return Some(d);
-		}
-	}
-
-	None
+pub fn mnormal_data(frame: u8) -> Option<&'static MoveData> {
+	MNORMAL_FRAMES.get(frame as usize).copied()
 }
 
 pub const NSPECIAL_DATA: [MoveData; 12] = [
@@ -638,18 +639,11 @@ pub const NSPECIAL_DATA: [MoveData; 12] = [
 	},
 ];
 
-pub fn nspecial_data(frame: u8) -> Option<&'static MoveData> {
-	let mut frame = (frame + 1) as usize;
-
-	for d in NSPECIAL_DATA.iter() {
-		frame = frame.saturating_sub(d.duration as usize);
+static NSPECIAL_FRAMES: [&MoveData; move_length(&NSPECIAL_DATA) as usize] =
+	frame_table(&NSPECIAL_DATA);
 
-		if frame == 0 {
-			return Some(d);
-		}
-	}
-
-	None
+pub fn nspecial_data(frame: u8) -> Option<&'static MoveData> {
+	NSPECIAL_FRAMES.get(frame as usize).copied()
 }
 
 pub const MSPECIAL_DATA: [MoveData; 11] = [
@@ -764,18 +758,11 @@ pub const MSPECIAL_DATA: [MoveData; 11] = [
 	},
 ];
 
-pub fn mspecial_data(frame: u8) -> Option<&'static MoveData> {
-	let mut frame = (frame + 1) as usize;
-
-	for d in MSPECIAL_DATA.iter() {
-		frame = frame.saturating_sub(d.duration as usize);
-
-		if frame == 0 {
-			return Some(d);
-		}
-	}
+static MSPECIAL_FRAMES: [&MoveData; move_length(&MSPECIAL_DATA) as usize] =
+	frame_table(&MSPECIAL_DATA);
 
-	None
+pub fn mspecial_data(frame: u8) -> Option<&'static MoveData> {
+	MSPECIAL_FRAMES.get(frame as usize).copied()
 }
 
 pub const FDASH_DATA: [MoveData; 9] = [
@@ -852,18 +839,11 @@ pub const FDASH_DATA: [MoveData; 9] = [
 	},
 ];
 
-pub fn fdash_data(frame: u8) -> Option<&'static MoveData> {
-	let mut frame = (frame + 1) as usize;
+static FDASH_FRAMES: [&MoveData; move_length(&FDASH_DATA) as usize] =
+	frame_table(&FDASH_DATA);
 
-	for d in FDASH_DATA.iter() {
-		frame = frame.saturating_sub(d.duration as usize);
-
-		if frame == 0 {
-			return Some(d);
-		}
-	}
-
-	None
+pub fn fdash_data(frame: u8) -> Option<&'static MoveData> {
+	FDASH_FRAMES.get(frame as usize).copied()
 }
 
 pub const BDASH_DATA: [MoveData; 8] = [
@@ -930,18 +910,11 @@ pub const BDASH_DATA: [MoveData; 8] = [
 	},
 ];
 
-pub fn bdash_data(frame: u8) -> Option<&'static MoveData> {
-	let mut frame = (frame + 1) as usize;
-
-	for d in BDASH_DATA.iter() {
-		frame = frame.saturating_sub(d.duration as usize);
+static BDASH_FRAMES: [&MoveData; move_length(&BDASH_DATA) as usize] =
+	frame_table(&BDASH_DATA);
 
-		if frame == 0 {
-			return Some(d);
-		}
-	}
-
-	None
+pub fn bdash_data(frame: u8) -> Option<&'static MoveData> {
+	BDASH_FRAMES.get(frame as usize).copied()
 }
 
 pub const HIT_DATA: [MoveData; 5] = [
@@ -985,18 +958,11 @@ pub const HIT_DATA: [MoveData; 5] = [
 	},
 ];
 
-pub fn hit_data(frame: u8) -> Option<&'static MoveData> {
-	let mut frame = (frame + 1) as usize;
-
-	for d in HIT_DATA.iter() {
-		frame = frame.saturating_sub(d.duration as usize);
+static HIT_FRAMES: [&MoveData; move_length(&HIT_DATA) as usize] =
+	frame_table(&HIT_DATA);
 
-		if frame == 0 {
-			return Some(d);
-		}
-	}
-
-	None
+pub fn hit_data(frame: u8) -> Option<&'static MoveData> {
+	HIT_FRAMES.get(frame as usize).copied()
 }
 
 pub const HBLOCK_DATA: [MoveData; 5] = [
@@ -1040,18 +1006,11 @@ pub const HBLOCK_DATA: [MoveData; 5] = [
 	},
 ];
 
-pub fn hblock_data(frame: u8) -> Option<&'static MoveData> {
-	let mut frame = (frame + 1) as usize;
+static HBLOCK_FRAMES: [&MoveData; move_length(&HBLOCK_DATA) as usize] =
+	frame_table(&HBLOCK_DATA);
 
-	for d in HBLOCK_DATA.iter() {
-		frame = frame.saturating_sub(d.duration as usize);
-
-		if frame == 0 {
-			return Some(d);
-		}
-	}
-
-	None
+pub fn hblock_data(frame: u8) -> Option<&'static MoveData> {
+	HBLOCK_FRAMES.get(frame as usize).copied()
 }
 
 pub const LBLOCK_DATA: [MoveData; 5] = [
@@ -1095,18 +1054,11 @@ pub const LBLOCK_DATA: [MoveData; 5] = [
 	},
 ];
 
-pub fn lblock_data(frame: u8) -> Option<&'static MoveData> {
-	let mut frame = (frame + 1) as usize;
-
-	for d in LBLOCK_DATA.iter() {
-		frame = frame.saturating_sub(d.duration as usize);
+static LBLOCK_FRAMES: [&MoveData; move_length(&LBLOCK_DATA) as usize] =
+	frame_table(&LBLOCK_DATA);
 
-		if frame == 0 {
-			return Some(d);
-		}
-	}
-
-	None
+pub fn lblock_data(frame: u8) -> Option<&'static MoveData> {
+	LBLOCK_FRAMES.get(frame as usize).copied()
 }
 
 pub const GUARD_BREAK_DATA: [MoveData; 5] = [
@@ -1154,18 +1106,11 @@ pub const GUARD_BREAK_DATA: [MoveData; 5] = [
 	},
 ];
 
-pub fn guard_break_data(frame: u8) -> Option<&'static MoveData> {
-	let mut frame = (frame + 1) as usize;
-
-	for d in GUARD_BREAK_DATA.iter() {
-		frame = frame.saturating_sub(d.duration as usize);
+static GUARD_BREAK_FRAMES: [&MoveData; move_length(&GUARD_BREAK_DATA) as usize] =
+	frame_table(&GUARD_BREAK_DATA);
 
-		if frame == 0 {
-			return Some(d);
-		}
-	}
-
-	None
+pub fn guard_break_data(frame: u8) -> Option<&'static MoveData> {
+	GUARD_BREAK_FRAMES.get(frame as usize).copied()
 }
 
 pub fn dead_data() -> &'static MoveData {
@@ -1180,6 +1125,27 @@ pub fn dead_data() -> &'static MoveData {
 	&DATA
 }
 
+/// The `MoveData` of every frame of a move, built at compile time so looking a frame up is a
+/// single index instead of a walk through the move. `LEN` must be the `move_length` of `data`.
+const fn frame_table<const LEN: usize>(data: &'static [MoveData]) -> [&'static MoveData; LEN] {
+	let mut res = [&data[0]; LEN];
+	let mut frame = 0;
+	let mut i = 0;
+
+	while i < data.len() {
+		let mut j = 0;
+		while j < data[i].duration {
+			res[frame] = &data[i];
+			frame += 1;
+			j += 1;
+		}
+		i += 1;
+	}
+
+	assert!(frame == LEN, "LEN is not the length of the move");
+	res
+}
+
 pub const fn move_length(data: &[MoveData]) -> u8 {
 	let mut i = 0;
 	let mut res = 0;
@@ -1192,3 +1158,89 @@ pub const fn move_length(data: &[MoveData]) -> u8 {
 
 	res
 }
+
+#[cfg(test)]
+mod test {
+	use std::mem;
+
+	use super::*;
+
+	type Lookup = fn(u8) -> Option<&'static MoveData>;
+
+	/// Index of the frame's `MoveData` by walking the move, like before the frame tables.
+	fn walk(data: &[MoveData], frame: u8) -> Option<usize> {
+		let mut frame = frame as usize + 1;
+
+		for (i, d) in data.iter().enumerate() {
+			frame = frame.saturating_sub(d.duration as usize);
+
+			if frame == 0 {
+				return Some(i);
+			}
+		}
+
+		None
+	}
+
+	#[test]
+	fn frame_tables() {
+		let moves: [(&[MoveData], Lookup); 13] = [
+			(&IDLE_DATA, idle_data),
+			(&FWALK_DATA, fwalk_data),
+			(&BWALK_DATA, bwalk_data),
+			(&NNORMAL_DATA, nnormal_data),
+			(&MNORMAL_DATA, mnormal_data),
+			(&NSPECIAL_DATA, nspecial_data),
+			(&MSPECIAL_DATA, mspecial_data),
+			(&FDASH_DATA, fdash_data),
+			(&BDASH_DATA, bdash_data),
+			(&HIT_DATA, hit_data),
+			(&HBLOCK_DATA, hblock_data),
+			(&LBLOCK_DATA, lblock_data),
+			(&GUARD_BREAK_DATA, guard_break_data),
+		];
+
+		for (data, lookup) in moves {
+			// Every use of a const array may be a different copy, so the indices are compared
+			let first = lookup(0).unwrap() as *const MoveData as usize;
+			let index = |frame| {
+				lookup(frame).map(|d| {
+					(d as *const MoveData as usize - first) / mem::size_of::<MoveData>()
+				})
+			};
+
+			for frame in 0..=u8::MAX {
+				assert_eq!(index(frame), walk(data, frame));
+			}
+		}
+	}
+
+	#[test]
+	fn world() {
+		let cbox = CBox {
+			offsetx: 10,
+			offsety: 5,
+			x: 20,
+			y: 30,
+		};
+
+		assert_eq!(
+			cbox.world(100, false),
+			WorldBox {
+				x1: 110,
+				y1: 5,
+				x2: 130,
+				y2: 35
+			}
+		);
+		assert_eq!(
+			cbox.world(100, true),
+			WorldBox {
+				x1: 70,
+				y1: 5,
+				x2: 90,
+				y2: 35
+			}
+		);
+	}
+}
@@ -0,0 +1,320 @@
+use std::{
+	fmt::Write as _,
+	fs::File,
+	io::{self, BufWriter, Write},
+	path::Path,
+};
+
+use godot::prelude::*;
+
+use crate::{
+	player::PlayerState,
+	replay::Replay,
+	simul::{self, Match},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram2D {
+	pub width: usize,
+	pub height: usize,
+	/// Row major, `height` rows of `width` cells
+	pub counts: Vec<u32>,
+}
+
+impl Histogram2D {
+	const CELL_SIZE: usize = 8;
+
+	pub fn new(width: usize, height: usize) -> Self {
+		Histogram2D {
+			width,
+			height,
+			counts: vec![0; width * height],
+		}
+	}
+
+	#[inline]
+	pub fn add(&mut self, x: usize, y: usize, amount: u32) {
+		self.counts[y * self.width + x] += amount;
+	}
+
+	#[inline]
+	pub fn get(&self, x: usize, y: usize) -> u32 {
+		self.counts[y * self.width + x]
+	}
+
+	/// Sums the rows, a 2D histogram with a single row is a 1D one.
+	pub fn collapse_rows(&self) -> Self {
+		let mut res = Self::new(self.width, 1);
+
+		for y in 0..self.height {
+			for x in 0..self.width {
+				res.add(x, 0, self.get(x, y));
+			}
+		}
+
+		res
+	}
+
+	/// One line per row, first column is the row label and the header holds the column labels.
+	pub fn to_csv(&self, col_labels: &[String], row_labels: &[String]) -> String {
+		let mut res = String::new();
+
+		res.push_str("bin");
+		for label in col_labels {
+			res.push(',');
+			res.push_str(label);
+		}
+		res.push('\n');
+
+		for (y, label) in row_labels.iter().enumerate() {
+			res.push_str(label);
+			for x in 0..self.width {
+				// Writing to a String can not fail
+				write!(res, ",{}", self.get(x, y)).unwrap();
+			}
+			res.push('\n');
+		}
+
+		res
+	}
+
+	pub fn to_svg(&self) -> String {
+		let size = Self::CELL_SIZE;
+		let mut res = format!(
+			r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}">"#,
+			self.width * size,
+			self.height * size
+		);
+		res.push('\n');
+
+		let max = self.max();
+		for y in 0..self.height {
+			for x in 0..self.width {
+				let [r, g, b] = heat_color(self.get(x, y), max);
+				writeln!(
+					res,
+					r#"<rect x="{}" y="{}" width="{size}" height="{size}" fill="rgb({r},{g},{b})"/>"#,
+					x * size,
+					y * size,
+				)
+				.unwrap();
+			}
+		}
+
+		res.push_str("</svg>\n");
+		res
+	}
+
+	pub fn write_png(&self, writer: impl Write) -> io::Result<()> {
+		let size = Self::CELL_SIZE;
+		let (width, height) = (self.width * size, self.height * size);
+		let max = self.max();
+
+		let mut pixels = Vec::with_capacity(width * height * 3);
+		for py in 0..height {
+			for px in 0..width {
+				pixels.extend(heat_color(self.get(px / size, py / size), max));
+			}
+		}
+
+		let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+		encoder.set_color(png::ColorType::Rgb);
+		encoder.set_depth(png::BitDepth::Eight);
+		encoder.write_header()?.write_image_data(&pixels)?;
+
+		Ok(())
+	}
+
+	#[inline]
+	fn max(&self) -> u32 {
+		self.counts.iter().copied().max().unwrap_or(0)
+	}
+}
+
+/// Black for empty cells, then blue to red as the count grows.
+fn heat_color(count: u32, max: u32) -> [u8; 3] {
+	if count == 0 || max == 0 {
+		return [0, 0, 0];
+	}
+
+	let t = count as f32 / max as f32;
+	[
+		(255.0 * t) as u8,
+		(64.0 * (1.0 - t)) as u8,
+		(255.0 * (1.0 - t)) as u8,
+	]
+}
+
+/// Aggregates spacing and positions of many frames, rounds and replays.
+#[derive(Debug, Clone, GodotClass)]
+#[class(no_init)]
+pub struct Heatmaps {
+	/// Distance bins x `PlayerState` entered, for both players
+	pub distance_action: Histogram2D,
+	/// Relative position bins x outcome of the round for that player
+	pub position_outcome: Histogram2D,
+	round_positions: [Histogram2D; 2],
+	prev_states: Option<[i64; 2]>,
+}
+
+#[godot_api]
+impl Heatmaps {
+	pub const BIN_SIZE: i16 = 30;
+	const BINS: usize = (Match::STAGE_LEN / Self::BIN_SIZE) as usize + 1;
+	const OUTCOMES: [&str; 3] = ["win", "loss", "draw"];
+
+	#[func]
+	pub fn gd_new() -> Gd<Self> {
+		Gd::from_object(Self::new())
+	}
+
+	pub fn new() -> Self {
+		Heatmaps {
+			distance_action: Histogram2D::new(
+				Self::BINS,
+				PlayerState::STATE_COUNT as usize,
+			),
+			position_outcome: Histogram2D::new(Self::BINS, Self::OUTCOMES.len()),
+			round_positions: [
+				Histogram2D::new(Self::BINS, 1),
+				Histogram2D::new(Self::BINS, 1),
+			],
+			prev_states: None,
+		}
+	}
+
+	#[func]
+	pub fn gd_record(&mut self, sim: Gd<Match>, result: simul::Result) {
+		self.record(&sim.bind(), result);
+	}
+
+	#[func]
+	pub fn gd_record_replay(&mut self, replay: Gd<Replay>) {
+		self.record_replay(&replay.bind());
+	}
+
+	pub fn record_replay(&mut self, replay: &Replay) {
+		replay.play(|sim, res| self.record(sim, res));
+	}
+
+	/// Must be called after every `Match::frame_update`, with its result.
+	pub fn record(&mut self, sim: &Match, result: simul::Result) {
+		let states = [sim.player_state(true), sim.player_state(false)];
+		let distance = Self::bin(sim.player_distance());
+
+		if sim.is_active() {
+			for (i, p1) in [true, false].into_iter().enumerate() {
+				let changed =
+					self.prev_states.is_none_or(|prev| prev[i] != states[i]);
+				if changed {
+					self.distance_action.add(distance, states[i] as usize, 1);
+				}
+
+				let pos = Self::bin(sim.player_relative_pos(p1));
+				self.round_positions[i].add(pos, 0, 1);
+			}
+			self.prev_states = Some(states);
+		}
+
+		let outcomes = match result {
+			simul::Result::Continue | simul::Result::Pause | simul::Result::Timeout => {
+				return
+			}
+			simul::Result::Player1 => [0, 1],
+			simul::Result::Player2 => [1, 0],
+			simul::Result::Draw => [2, 2],
+		};
+
+		for (positions, outcome) in self.round_positions.iter_mut().zip(outcomes) {
+			for x in 0..Self::BINS {
+				self.position_outcome.add(x, outcome, positions.get(x, 0));
+			}
+			*positions = Histogram2D::new(Self::BINS, 1);
+		}
+		self.prev_states = None;
+	}
+
+	/// Writes every histogram as CSV, SVG and PNG into `dir`, returns whether it succeeded.
+	#[func]
+	pub fn gd_save(&self, dir: GString) -> bool {
+		self.save(dir.to_string()).is_ok()
+	}
+
+	pub fn save(&self, dir: impl AsRef<Path>) -> io::Result<()> {
+		let dir = dir.as_ref();
+		let bins = (0..Self::BINS)
+			.map(|x| (x as i16 * Self::BIN_SIZE).to_string())
+			.collect::<Vec<_>>();
+		let states = (0..PlayerState::STATE_COUNT)
+			.map(|x| x.to_string())
+			.collect::<Vec<_>>();
+		let outcomes = Self::OUTCOMES.map(String::from);
+		let distance = self.distance_action.collapse_rows();
+		let position = self.position_outcome.collapse_rows();
+
+		let histograms = [
+			("distance", &distance, &["count".to_string()][..]),
+			("distance_action", &self.distance_action, &states[..]),
+			("position", &position, &["count".to_string()][..]),
+			("position_outcome", &self.position_outcome, &outcomes[..]),
+		];
+
+		for (name, hist, rows) in histograms {
+			std::fs::write(dir.join(format!("{name}.csv")), hist.to_csv(&bins, rows))?;
+			std::fs::write(dir.join(format!("{name}.svg")), hist.to_svg())?;
+			let png = BufWriter::new(File::create(dir.join(format!("{name}.png")))?);
+			hist.write_png(png)?;
+		}
+
+		Ok(())
+	}
+
+	#[inline]
+	fn bin(value: i16) -> usize {
+		(value / Self::BIN_SIZE) as usize
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{Heatmaps, Histogram2D};
+	use crate::{
+		input::{FgInput, InputConfig},
+		replay::Replay,
+	};
+
+	#[test]
+	fn csv() {
+		let mut hist = Histogram2D::new(2, 2);
+		hist.add(0, 0, 1);
+		hist.add(1, 1, 3);
+
+		let labels = ["a".to_string(), "b".to_string()];
+		assert_eq!(hist.to_csv(&labels, &labels), "bin,a,b\na,1,0\nb,0,3\n");
+		assert_eq!(hist.collapse_rows().counts, vec![1, 3]);
+	}
+
+	#[test]
+	fn timeout_replay() {
+		let mut replay = Replay::new(InputConfig::human(), InputConfig::human());
+		for _ in 0..4000 {
+			replay.push(
+				FgInput::new(0, 0, false, false),
+				FgInput::new(0, 0, false, false),
+			);
+		}
+
+		let mut heatmaps = Heatmaps::new();
+		heatmaps.record_replay(&replay);
+
+		// Nobody moves, so both players spend the whole round at their starting position
+		let total: u32 = heatmaps.position_outcome.counts.iter().sum();
+		let draws: u32 = (0..Heatmaps::BINS)
+			.map(|x| heatmaps.position_outcome.get(x, 2))
+			.sum();
+		assert!(total > 0);
+		assert_eq!(total, draws);
+		// Both players enter idle once per round, the second round has started as well
+		assert_eq!(heatmaps.distance_action.get(Heatmaps::bin(730), 0), 4);
+	}
+}
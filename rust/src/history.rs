@@ -0,0 +1,141 @@
+use godot::prelude::*;
+
+use crate::input::FgInput;
+
+/// An input and how many frames in a row it was held.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HistoryEntry {
+	pub input: FgInput,
+	pub frames: u16,
+}
+
+impl HistoryEntry {
+	/// Adds a frame if `input` is the same input, returns whether it was.
+	#[inline]
+	pub const fn merge(&mut self, input: FgInput) -> bool {
+		// `PartialEq` is not const
+		let same = self.input.movement == input.movement
+			&& self.input.movement_press == input.movement_press
+			&& self.input.attack_press == input.attack_press
+			&& self.input.attack_hold == input.attack_hold;
+
+		if same {
+			self.frames = self.frames.saturating_add(1);
+		}
+
+		same
+	}
+
+	pub fn to_dictionary(self) -> Dictionary {
+		let mut res = Dictionary::new();
+
+		res.set("movement", self.input.movement);
+		res.set("movement_press", self.input.movement_press);
+		res.set("attack_press", self.input.attack_press);
+		res.set("attack_hold", self.input.attack_hold);
+		res.set("frames", self.frames);
+
+		res
+	}
+}
+
+/// Ring buffer of the last inputs of a player, like the input log of training modes. Presses
+/// last a single frame, so they always get their own entry.
+#[derive(Debug, Clone, Copy, Hash)]
+pub struct InputHistory {
+	entries: [HistoryEntry; Self::LEN],
+	/// Index of the newest entry
+	head: usize,
+	len: usize,
+}
+
+impl InputHistory {
+	pub const LEN: usize = 32;
+
+	pub const fn new() -> Self {
+		InputHistory {
+			entries: [HistoryEntry {
+				input: FgInput::new(0, 0, false, false),
+				frames: 0,
+			}; Self::LEN],
+			head: 0,
+			len: 0,
+		}
+	}
+
+	pub const fn push(&mut self, input: FgInput) {
+		if self.len > 0 {
+			if self.entries[self.head].merge(input) {
+				return;
+			}
+
+			self.head = (self.head + 1) % Self::LEN;
+		}
+
+		self.entries[self.head] = HistoryEntry { input, frames: 1 };
+		if self.len < Self::LEN {
+			self.len += 1;
+		}
+	}
+
+	/// Newest entry first.
+	pub fn iter(&self) -> impl Iterator<Item = HistoryEntry> + '_ {
+		(0..self.len).map(|i| self.entries[(self.head + Self::LEN - i) % Self::LEN])
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{HistoryEntry, InputHistory};
+	use crate::input::FgInput;
+
+	const NONE: FgInput = FgInput::new(0, 0, false, false);
+	const FORWARD: FgInput = FgInput::new(1, 0, false, false);
+	const FORWARD_PRESS: FgInput = FgInput::new(1, 1, false, false);
+
+	#[test]
+	fn merge() {
+		let mut history = InputHistory::new();
+		assert_eq!(history.iter().count(), 0);
+
+		history.push(NONE);
+		history.push(NONE);
+		history.push(FORWARD_PRESS);
+		history.push(FORWARD);
+		history.push(FORWARD);
+		history.push(FORWARD);
+
+		let entries = history.iter().collect::<Vec<_>>();
+		assert_eq!(
+			entries,
+			[
+				HistoryEntry {
+					input: FORWARD,
+					frames: 3
+				},
+				HistoryEntry {
+					input: FORWARD_PRESS,
+					frames: 1
+				},
+				HistoryEntry {
+					input: NONE,
+					frames: 2
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn wrap() {
+		let mut history = InputHistory::new();
+
+		for i in 0..InputHistory::LEN + 5 {
+			history.push([FORWARD, NONE][i % 2]);
+		}
+
+		let mut entries = history.iter();
+		assert_eq!(entries.next().unwrap().input, FORWARD);
+		assert_eq!(entries.next().unwrap().input, NONE);
+		assert_eq!(entries.count(), InputHistory::LEN - 2);
+	}
+}
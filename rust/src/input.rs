@@ -1,9 +1,10 @@
 use std::num::{NonZero, NonZeroU8};
 
 use godot::prelude::*;
+use serde::{Deserialize, Serialize};
 
 /// Inputs with attack overweighs Inputs with no attack.
-#[derive(Debug, PartialEq, Eq, Clone, Copy, GodotClass)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, GodotClass, Serialize, Deserialize)]
 #[class(no_init)]
 pub struct FgInput {
 	pub movement: i8,
@@ -43,12 +44,83 @@ impl FgInput {
 		}
 	}
 
-	pub const fn to_buffer(self) -> Option<ActionBuffer> {
-		ActionBuffer::new(self.movement, self.attack_press)
+	pub const fn to_buffer(self, buff_time: NonZeroU8) -> Option<ActionBuffer> {
+		ActionBuffer::with_time(self.movement, self.attack_press, buff_time)
 	}
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// Input leniency of a player. Frame counts are clamped to at least 1.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, GodotClass, Serialize, Deserialize)]
+#[class(no_init)]
+pub struct InputConfig {
+	/// Frames an attack press stays buffered, including the frame it was pressed on
+	pub buffer_time: u8,
+	/// Frames between the two taps of a dash
+	pub dash_time: u8,
+	/// Frames attack must be held for the special
+	pub charge_time: u8,
+	/// Holding a direction for `dash_time` frames also dashes, no double tap needed
+	pub hold_to_dash: bool,
+}
+
+#[godot_api]
+impl InputConfig {
+	#[func]
+	pub fn gd_new(
+		buffer_time: u8,
+		dash_time: u8,
+		charge_time: u8,
+		hold_to_dash: bool,
+	) -> Gd<Self> {
+		Gd::from_object(Self::new(buffer_time, dash_time, charge_time, hold_to_dash))
+	}
+
+	#[func]
+	pub fn gd_human() -> Gd<Self> {
+		Gd::from_object(Self::human())
+	}
+
+	#[func]
+	pub fn gd_bot() -> Gd<Self> {
+		Gd::from_object(Self::bot())
+	}
+
+	pub const fn new(
+		buffer_time: u8,
+		dash_time: u8,
+		charge_time: u8,
+		hold_to_dash: bool,
+	) -> Self {
+		InputConfig {
+			buffer_time: if buffer_time > 0 { buffer_time } else { 1 },
+			dash_time: if dash_time > 0 { dash_time } else { 1 },
+			charge_time: if charge_time > 0 { charge_time } else { 1 },
+			hold_to_dash,
+		}
+	}
+
+	pub const fn human() -> Self {
+		Self::new(ActionBuffer::BUFFER_TIME.get(), 10, 60, false)
+	}
+
+	/// Bots input exactly when they want to, so they get a tighter dash window.
+	pub const fn bot() -> Self {
+		InputConfig {
+			dash_time: 3,
+			..Self::human()
+		}
+	}
+
+	#[inline]
+	pub const fn preset(bot: bool) -> Self {
+		match bot {
+			true => Self::bot(),
+			false => Self::human(),
+		}
+	}
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub struct ActionBuffer {
 	pub movement: i8,
 	pub buff_time: NonZeroU8,
@@ -56,13 +128,23 @@ pub struct ActionBuffer {
 
 impl ActionBuffer {
 	// n - 1 is buffer length
-	const BUFFER_TIME: NonZeroU8 = NonZero::new(4).unwrap();
+	pub const BUFFER_TIME: NonZeroU8 = NonZero::new(4).unwrap();
 
+	#[cfg(test)]
+	#[inline]
 	pub const fn new(movement: i8, attack_press: bool) -> Option<Self> {
+		Self::with_time(movement, attack_press, Self::BUFFER_TIME)
+	}
+
+	pub const fn with_time(
+		movement: i8,
+		attack_press: bool,
+		buff_time: NonZeroU8,
+	) -> Option<Self> {
 		if attack_press {
 			Some(ActionBuffer {
 				movement,
-				buff_time: Self::BUFFER_TIME,
+				buff_time,
 			})
 		} else {
 			None
@@ -94,7 +176,8 @@ impl ActionBuffer {
 mod test {
 	use std::num::NonZero;
 
-	use super::ActionBuffer;
+	use super::{ActionBuffer, FgInput, InputConfig};
+	use crate::simul::Match;
 
 	#[test]
 	fn update_buffer() {
@@ -132,4 +215,34 @@ mod test {
 		assert_eq!(ActionBuffer::compare(input5, input6), input6);
 		assert_eq!(ActionBuffer::compare(input6, input7), input6);
 	}
+
+	#[test]
+	fn input_config() {
+		assert_eq!(InputConfig::human().buffer_time, 4);
+		assert_eq!(InputConfig::human().dash_time, 10);
+		assert_eq!(InputConfig::bot().dash_time, 3);
+		assert_eq!(
+			InputConfig::new(0, 0, 0, false),
+			InputConfig::new(1, 1, 1, false)
+		);
+
+		let config = InputConfig {
+			hold_to_dash: true,
+			..InputConfig::human()
+		};
+		let mut sim = Match::new(config, InputConfig::human());
+		let none = FgInput::new(0, 0, false, false);
+		while !sim.is_active() {
+			sim.step(none, none);
+		}
+
+		// Walks forward until the direction is held for the dash window
+		sim.step(FgInput::new(1, 1, false, false), none);
+		for _ in 1..config.dash_time {
+			assert_eq!(sim.player_state(true), 1);
+			sim.step(FgInput::new(1, 0, false, false), none);
+		}
+		sim.step(FgInput::new(1, 0, false, false), none);
+		assert_eq!(sim.player_state(true), 3);
+	}
 }
@@ -0,0 +1,323 @@
+use godot::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{rng::Rng, simul};
+
+/// How `OpponentPool::sample` picks the opponent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GodotConvert, Var, Export, Serialize, Deserialize)]
+#[godot(via = i64)]
+pub enum SamplingScheme {
+	/// Always the newest checkpoint, plain self-play
+	Latest,
+	Uniform,
+	/// Prioritised fictitious self-play, checkpoints the learner loses to are picked more
+	Pfsp,
+}
+
+/// A past policy and the learner's results against it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+	pub id: u32,
+	/// Whatever the frontend loads the policy from, usually an `.onnx` file
+	pub path: String,
+	pub wins: u32,
+	pub losses: u32,
+	pub draws: u32,
+}
+
+impl Checkpoint {
+	#[inline]
+	pub const fn games(&self) -> u32 {
+		self.wins + self.losses + self.draws
+	}
+
+	/// Of the learner, draws count as half a win. 0.5 before any game is played.
+	pub fn win_rate(&self) -> f32 {
+		match self.games() {
+			0 => 0.5,
+			games => (self.wins as f32 + self.draws as f32 / 2.0) / games as f32,
+		}
+	}
+}
+
+/// Historical policy checkpoints to play the learner against, so self-play does not collapse
+/// into beating only its latest self. Checkpoints are referred to by ids, which stay the same
+/// when old checkpoints are dropped.
+#[derive(Debug, Clone, Serialize, Deserialize, GodotClass)]
+#[class(no_init)]
+pub struct OpponentPool {
+	checkpoints: Vec<Checkpoint>,
+	pub scheme: SamplingScheme,
+	/// Oldest checkpoints are dropped past this, 0 for no limit
+	pub max_size: u32,
+	/// Sharpness of the PFSP weighting, higher focuses harder on the hardest opponents
+	pub pfsp_exponent: f32,
+	next_id: u32,
+	#[serde(skip, default = "OpponentPool::default_rng")]
+	rng: Rng,
+}
+
+#[godot_api]
+impl OpponentPool {
+	#[func]
+	pub fn gd_new(scheme: SamplingScheme, seed: i64) -> Gd<Self> {
+		Gd::from_object(Self::new(scheme, seed as u64))
+	}
+
+	pub const fn new(scheme: SamplingScheme, seed: u64) -> Self {
+		OpponentPool {
+			checkpoints: Vec::new(),
+			scheme,
+			max_size: 0,
+			pfsp_exponent: 2.0,
+			next_id: 0,
+			rng: Rng::new(seed),
+		}
+	}
+
+	const fn default_rng() -> Rng {
+		Rng::new(0)
+	}
+
+	#[func]
+	pub fn set_scheme(&mut self, scheme: SamplingScheme) {
+		self.scheme = scheme;
+	}
+
+	#[func]
+	pub fn set_max_size(&mut self, size: u32) {
+		self.max_size = size;
+		self.evict();
+	}
+
+	#[func]
+	pub fn set_pfsp_exponent(&mut self, exponent: f32) {
+		self.pfsp_exponent = exponent.max(0.0);
+	}
+
+	#[func]
+	pub fn set_seed(&mut self, seed: i64) {
+		self.rng = Rng::new(seed as u64);
+	}
+
+	/// Returns the id of the new checkpoint.
+	#[func]
+	pub fn add_checkpoint(&mut self, path: GString) -> i64 {
+		self.add(path.to_string()) as i64
+	}
+
+	pub fn add(&mut self, path: String) -> u32 {
+		let id = self.next_id;
+		self.next_id += 1;
+
+		self.checkpoints.push(Checkpoint {
+			id,
+			path,
+			wins: 0,
+			losses: 0,
+			draws: 0,
+		});
+		self.evict();
+
+		id
+	}
+
+	#[func]
+	pub fn len(&self) -> i64 {
+		self.checkpoints.len() as i64
+	}
+
+	#[func]
+	pub fn is_empty(&self) -> bool {
+		self.checkpoints.is_empty()
+	}
+
+	/// Id of the next opponent, -1 if the pool is empty.
+	#[func]
+	pub fn gd_sample(&mut self) -> i64 {
+		self.sample().map_or(-1, |checkpoint| checkpoint.id as i64)
+	}
+
+	pub fn sample(&mut self) -> Option<&Checkpoint> {
+		let index = match self.scheme {
+			SamplingScheme::Latest => self.checkpoints.len().checked_sub(1)?,
+			SamplingScheme::Uniform => {
+				let last = self.checkpoints.len().checked_sub(1)?;
+				self.rng.range(0, last as i64) as usize
+			}
+			SamplingScheme::Pfsp => self.sample_pfsp()?,
+		};
+
+		self.checkpoints.get(index)
+	}
+
+	/// Weights are `(1 - win_rate) ^ exponent`, uniform if the learner beats them all.
+	fn sample_pfsp(&mut self) -> Option<usize> {
+		let weights: Vec<f32> = self
+			.checkpoints
+			.iter()
+			.map(|checkpoint| (1.0 - checkpoint.win_rate()).powf(self.pfsp_exponent))
+			.collect();
+		let total: f32 = weights.iter().sum();
+
+		if total <= 0.0 {
+			let last = self.checkpoints.len().checked_sub(1)?;
+			return Some(self.rng.range(0, last as i64) as usize);
+		}
+
+		let mut pick = self.rng.next_f32() * total;
+		for (i, weight) in weights.iter().enumerate() {
+			if pick < *weight {
+				return Some(i);
+			}
+			pick -= weight;
+		}
+
+		// Float rounding, the last checkpoint with any weight
+		weights.iter().rposition(|&weight| weight > 0.0)
+	}
+
+	#[func]
+	pub fn checkpoint_path(&self, id: i64) -> GString {
+		self.get(id as u32)
+			.map_or_else(GString::new, |checkpoint| GString::from(&checkpoint.path))
+	}
+
+	/// Learner's win rate against the checkpoint, -1 if there is no such checkpoint.
+	#[func]
+	pub fn win_rate(&self, id: i64) -> f32 {
+		self.get(id as u32).map_or(-1.0, Checkpoint::win_rate)
+	}
+
+	/// Records a finished game against checkpoint `id`. Results other than a win or a loss
+	/// count as draws. Returns false if the checkpoint was dropped from the pool since.
+	#[func]
+	pub fn record_result(&mut self, id: i64, learner_p1: bool, result: simul::Result) -> bool {
+		let Some(checkpoint) = self.checkpoints.iter_mut().find(|c| c.id == id as u32)
+		else {
+			return false;
+		};
+
+		match (result, learner_p1) {
+			(simul::Result::Player1, true) | (simul::Result::Player2, false) => {
+				checkpoint.wins += 1
+			}
+			(simul::Result::Player1, false) | (simul::Result::Player2, true) => {
+				checkpoint.losses += 1
+			}
+			_ => checkpoint.draws += 1,
+		}
+
+		true
+	}
+
+	/// One dictionary per checkpoint, oldest first.
+	#[func]
+	pub fn checkpoints(&self) -> Array<Dictionary> {
+		self.checkpoints
+			.iter()
+			.map(|checkpoint| {
+				dict! {
+					"id": checkpoint.id,
+					"path": checkpoint.path.clone(),
+					"wins": checkpoint.wins,
+					"losses": checkpoint.losses,
+					"draws": checkpoint.draws,
+				}
+			})
+			.collect()
+	}
+
+	#[func]
+	pub fn to_json(&self) -> GString {
+		// Only plain numbers and strings, serialization can not fail
+		GString::from(serde_json::to_string(self).unwrap())
+	}
+
+	/// The sampling is reseeded with `seed`, it is not part of the JSON.
+	#[func]
+	pub fn from_json(json: GString, seed: i64) -> Option<Gd<Self>> {
+		serde_json::from_str::<Self>(&json.to_string())
+			.ok()
+			.map(|mut pool| {
+				pool.set_seed(seed);
+				Gd::from_object(pool)
+			})
+	}
+
+	pub fn get(&self, id: u32) -> Option<&Checkpoint> {
+		self.checkpoints
+			.iter()
+			.find(|checkpoint| checkpoint.id == id)
+	}
+
+	/// Drops the oldest checkpoints past `max_size`.
+	fn evict(&mut self) {
+		let max = self.max_size as usize;
+		if max > 0 && self.checkpoints.len() > max {
+			self.checkpoints.drain(..self.checkpoints.len() - max);
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{OpponentPool, SamplingScheme};
+	use crate::simul;
+
+	fn pool(scheme: SamplingScheme) -> OpponentPool {
+		let mut pool = OpponentPool::new(scheme, 0);
+		for i in 0..4 {
+			pool.add(format!("policy_{i}.onnx"));
+		}
+		pool
+	}
+
+	#[test]
+	fn schemes() {
+		assert!(OpponentPool::new(SamplingScheme::Uniform, 0)
+			.sample()
+			.is_none());
+
+		let mut latest = pool(SamplingScheme::Latest);
+		assert_eq!(latest.sample().unwrap().id, 3);
+
+		let mut uniform = pool(SamplingScheme::Uniform);
+		let mut counts = [0; 4];
+		for _ in 0..4000 {
+			counts[uniform.sample().unwrap().id as usize] += 1;
+		}
+		assert!(counts.iter().all(|&count| (800..1200).contains(&count)));
+
+		// The learner always beats 0 to 2 and always loses to 3
+		let mut pfsp = pool(SamplingScheme::Pfsp);
+		for id in 0..4 {
+			let result = match id {
+				3 => simul::Result::Player2,
+				_ => simul::Result::Player1,
+			};
+			assert!(pfsp.record_result(id, true, result));
+		}
+		assert_eq!(pfsp.win_rate(3), 0.0);
+		for _ in 0..100 {
+			assert_eq!(pfsp.sample().unwrap().id, 3);
+		}
+	}
+
+	#[test]
+	fn eviction() {
+		let mut pool = pool(SamplingScheme::Latest);
+		pool.set_max_size(2);
+		assert_eq!(pool.len(), 2);
+		assert!(pool.get(1).is_none());
+		assert!(!pool.record_result(1, true, simul::Result::Player1));
+
+		// Ids are never reused
+		assert_eq!(pool.add("policy_4.onnx".to_string()), 4);
+		assert_eq!(pool.checkpoints.first().unwrap().id, 3);
+
+		assert!(pool.record_result(4, false, simul::Result::Player1));
+		assert!(pool.record_result(4, false, simul::Result::Timeout));
+		assert_eq!(pool.win_rate(4), 0.25);
+	}
+}
@@ -1,8 +1,28 @@
-mod framedata;
-mod input;
-mod player;
-mod simul;
+// `#[godot_api]` generates closures returning `CallError`, which is large by design.
+#![allow(clippy::result_large_err)]
+
+mod advantage;
+pub mod dataset;
+pub mod decoder;
+mod delay;
+mod domain;
+pub mod framedata;
+mod heatmap;
+mod history;
+pub mod input;
+mod league;
+mod pixelobs;
+pub mod player;
+mod recognizer;
+mod recorder;
+pub mod render;
+pub mod replay;
+pub mod rng;
+mod scenario;
+pub mod simul;
+pub mod stats;
 mod timer;
+mod training;
 
 use godot::prelude::*;
 
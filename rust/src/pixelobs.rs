@@ -0,0 +1,203 @@
+use godot::prelude::*;
+
+use crate::{framedata::WorldBox, simul::Match};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GodotConvert, Var, Export)]
+#[godot(via = i64)]
+pub enum PixelObsMode {
+	/// Single channel, bodies and hitboxes drawn in different shades.
+	Grayscale,
+	/// One channel each for the body, hurtboxes and hitbox of the player, then the opponent.
+	Semantic,
+}
+
+impl PixelObsMode {
+	#[inline]
+	pub const fn channels(self) -> usize {
+		match self {
+			PixelObsMode::Grayscale => 1,
+			PixelObsMode::Semantic => 6,
+		}
+	}
+}
+
+/// Rasterizes the boxes of a match into a low resolution image, for agents that learn from
+/// pixels. The image is seen from the player's side: the player is always on the left, facing
+/// right, just like `Match::player_obs`.
+#[derive(Debug, Clone, Copy)]
+pub struct PixelObs {
+	pub width: usize,
+	pub height: usize,
+	pub mode: PixelObsMode,
+}
+
+impl PixelObs {
+	/// Stage units visible past each wall, so boxes at the walls are not cut
+	const MARGIN: i16 = 250;
+	/// Highest visible point, above every box
+	const TOP: i16 = 320;
+
+	const OWN_BODY: f32 = 0.6;
+	const OPPONENT_BODY: f32 = 0.3;
+	const HITBOX: f32 = 1.0;
+	/// Largest width and height, far past what agents learn from
+	pub const MAX_SIZE: usize = 512;
+
+	pub const fn new(width: usize, height: usize, mode: PixelObsMode) -> Self {
+		PixelObs {
+			width,
+			height,
+			mode,
+		}
+	}
+
+	/// Number of values in an observation
+	#[inline]
+	pub const fn len(&self) -> usize {
+		self.width * self.height * self.mode.channels()
+	}
+
+	/// Channel major, rows from top to bottom, values in `[0, 1]`.
+	pub fn observe(&self, sim: &Match, p1: bool) -> Vec<f32> {
+		let mut res = vec![0.0; self.len()];
+
+		for (i, player) in [p1, !p1].into_iter().enumerate() {
+			match self.mode {
+				PixelObsMode::Grayscale => {
+					let shade = match i {
+						0 => Self::OWN_BODY,
+						_ => Self::OPPONENT_BODY,
+					};
+					self.fill(
+						&mut res,
+						0,
+						self.view(sim.collision_box(player), p1),
+						shade,
+					);
+				}
+				PixelObsMode::Semantic => {
+					let channel = i * 3;
+					self.fill(
+						&mut res,
+						channel,
+						self.view(sim.collision_box(player), p1),
+						1.0,
+					);
+					for hurtbox in sim.hurtboxes(player) {
+						self.fill(
+							&mut res,
+							channel + 1,
+							self.view(hurtbox, p1),
+							1.0,
+						);
+					}
+				}
+			}
+		}
+
+		// Hitboxes last, so they are drawn over the bodies
+		for (i, player) in [p1, !p1].into_iter().enumerate() {
+			if let Some(hitbox) = sim.hitbox(player) {
+				let channel = match self.mode {
+					PixelObsMode::Grayscale => 0,
+					PixelObsMode::Semantic => i * 3 + 2,
+				};
+				self.fill(&mut res, channel, self.view(hitbox, p1), Self::HITBOX);
+			}
+		}
+
+		res
+	}
+
+	/// Mirrors the box for player 2, so everyone sees the stage from the left.
+	#[inline]
+	fn view(&self, wbox: WorldBox, p1: bool) -> WorldBox {
+		match p1 {
+			true => wbox,
+			false => WorldBox {
+				x1: Match::STAGE_LEN - wbox.x2,
+				x2: Match::STAGE_LEN - wbox.x1,
+				..wbox
+			},
+		}
+	}
+
+	/// Sets every pixel whose center is inside the box.
+	fn fill(&self, res: &mut [f32], channel: usize, wbox: WorldBox, value: f32) {
+		let view_len = (Match::STAGE_LEN + 2 * Self::MARGIN) as f32;
+		let pixel_width = view_len / self.width as f32;
+		let pixel_height = Self::TOP as f32 / self.height as f32;
+
+		let col = |x: i16| {
+			let x = (x + Self::MARGIN) as f32 / pixel_width - 0.5;
+			(x.ceil().max(0.0) as usize).min(self.width)
+		};
+		let row = |y: i16| {
+			let y = (Self::TOP - y) as f32 / pixel_height - 0.5;
+			(y.ceil().max(0.0) as usize).min(self.height)
+		};
+
+		let plane =
+			&mut res[channel * self.width * self.height..][..self.width * self.height];
+
+		for y in row(wbox.y2)..row(wbox.y1) {
+			for x in col(wbox.x1)..col(wbox.x2) {
+				plane[y * self.width + x] = value;
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{PixelObs, PixelObsMode};
+	use crate::{
+		input::{FgInput, InputConfig},
+		simul::Match,
+	};
+
+	#[test]
+	fn mirrored() {
+		let mut sim = Match::new(InputConfig::human(), InputConfig::human());
+		let obs = PixelObs::new(64, 16, PixelObsMode::Semantic);
+
+		// Players start at mirrored positions
+		assert_eq!(obs.observe(&sim, true), obs.observe(&sim, false));
+
+		let forward = FgInput::new(1, 0, false, false);
+		let none = FgInput::new(0, 0, false, false);
+		while !sim.is_active() {
+			sim.step(none, none);
+		}
+		for _ in 0..30 {
+			sim.step(forward, none);
+		}
+
+		let p1 = obs.observe(&sim, true);
+		let p2 = obs.observe(&sim, false);
+		assert_eq!(p1.len(), obs.len());
+		assert_ne!(p1, p2);
+
+		// Own body is on the left half, the opponent's on the right half
+		let plane = 64 * 16;
+		let bottom = |channel: usize, obs: &[f32]| {
+			let row = &obs[channel * plane + 15 * 64..][..64];
+			row.iter().position(|&x| x == 1.0).unwrap()
+		};
+		for obs in [&p1, &p2] {
+			assert!(bottom(0, obs) < 32);
+			assert!(bottom(3, obs) >= 32);
+		}
+	}
+
+	#[test]
+	fn grayscale() {
+		let sim = Match::new(InputConfig::human(), InputConfig::human());
+		let obs = PixelObs::new(32, 8, PixelObsMode::Grayscale).observe(&sim, true);
+
+		assert_eq!(obs.len(), 32 * 8);
+		assert!(obs.contains(&PixelObs::OWN_BODY));
+		assert!(obs.contains(&PixelObs::OPPONENT_BODY));
+		assert!(!obs.contains(&PixelObs::HITBOX));
+	}
+}
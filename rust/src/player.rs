@@ -1,15 +1,22 @@
+use std::num::NonZeroU8;
+
+use godot::prelude::*;
+use serde::{Deserialize, Serialize};
+
 use crate::{
 	framedata::*,
-	input::{ActionBuffer, FgInput},
+	history::InputHistory,
+	input::{ActionBuffer, FgInput, InputConfig},
 	simul::Match,
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Hash)]
 pub struct Player {
 	pub position: i16,
 	pub wins: u8,
 	pub guard_points: u8,
 	pub counter_hit: bool,
+	pub history: InputHistory,
 	state: PlayerState,
 	normal_buff: Option<ActionBuffer>,
 	special_buff: Option<ActionBuffer>,
@@ -17,22 +24,21 @@ pub struct Player {
 	fdash_timer: u8,
 	bdash_timer: u8,
 	movement: i8,
+	movement_hold: u8,
 	input_hold: u8,
-	bot: bool,
+	input_config: InputConfig,
+	handicap: Handicap,
+	/// Enders taken this round, see `Handicap::enders_to_ko`
+	enders_taken: u8,
 }
 
 impl Player {
-	const MAX_GUARD: u8 = 3;
-	const SPECIAL_CHARGE_TIME: u8 = 60;
-	const PLAYER_DASH_TIME: u8 = 10;
-	const BOT_DASH_TIME: u8 = 3;
-
 	#[inline]
-	pub const fn new(start_pos: i16, bot: bool) -> Self {
+	pub const fn new(start_pos: i16, input_config: InputConfig) -> Self {
 		Player {
 			position: start_pos,
 			wins: 0,
-			guard_points: Self::MAX_GUARD,
+			guard_points: Handicap::NONE.start_guard,
 			state: PlayerState::Idle(0),
 			normal_buff: None,
 			special_buff: None,
@@ -40,28 +46,52 @@ impl Player {
 			fdash_timer: 0,
 			bdash_timer: 0,
 			movement: 0,
+			movement_hold: 0,
 			input_hold: 0,
 			counter_hit: false,
-			bot,
+			history: InputHistory::new(),
+			input_config,
+			handicap: Handicap::NONE,
+			enders_taken: 0,
 		}
 	}
 
+	/// Wins, the input history, the input config and the handicap are kept.
 	#[inline]
 	pub const fn reset(&mut self, start_pos: i16) {
 		*self = Player {
 			wins: self.wins,
-			..Player::new(start_pos, self.bot)
+			history: self.history,
+			guard_points: self.handicap.start_guard,
+			handicap: self.handicap,
+			..Player::new(start_pos, self.input_config)
 		};
 	}
 
+	/// Same player on the other side of the stage.
+	#[inline]
+	pub fn mirrored(&self) -> Self {
+		Player {
+			position: Match::STAGE_LEN - self.position,
+			..self.clone()
+		}
+	}
+
 	pub const fn set_input(&mut self, input: FgInput) {
+		self.history.push(input);
+		self.movement_hold = match input.movement {
+			0 => 0,
+			movement if movement == self.movement => self.movement_hold.saturating_add(1),
+			_ => 1,
+		};
 		self.movement = input.movement;
-		self.normal_buff = ActionBuffer::compare(self.normal_buff, input.to_buffer());
+		self.normal_buff =
+			ActionBuffer::compare(self.normal_buff, input.to_buffer(self.buffer_time()));
 
 		// Dash
 		match input.movement_press {
 			1.. if self.fdash_timer > 0 => {
-				self.dash_buff = ActionBuffer::new(1, true);
+				self.dash_buff = ActionBuffer::with_time(1, true, self.buffer_time());
 				self.reset_dash_timer();
 			}
 			1.. => {
@@ -69,7 +99,7 @@ impl Player {
 				self.fdash_timer = self.dash_time();
 			}
 			..=-1 if self.bdash_timer > 0 => {
-				self.dash_buff = ActionBuffer::new(-1, true);
+				self.dash_buff = ActionBuffer::with_time(-1, true, self.buffer_time());
 				self.reset_dash_timer();
 			}
 			..=-1 => {
@@ -82,6 +112,10 @@ impl Player {
 			}
 		}
 
+		if self.input_config.hold_to_dash && self.movement_hold == self.dash_time() {
+			self.dash_buff = ActionBuffer::with_time(self.movement, true, self.buffer_time());
+		}
+
 		let old_hold = self.input_hold;
 
 		// Hold specials, the charge depends on current input, not buffer
@@ -92,8 +126,9 @@ impl Player {
 		};
 
 		// If holding is reset and hold time
-		if old_hold >= Self::SPECIAL_CHARGE_TIME && !input.attack_hold {
-			self.special_buff = ActionBuffer::new(input.movement, true);
+		if old_hold >= self.charge_time() && !input.attack_hold {
+			self.special_buff =
+				ActionBuffer::with_time(input.movement, true, self.buffer_time());
 		} else {
 			self.special_buff = None;
 		}
@@ -294,32 +329,55 @@ impl Player {
 	}
 
 	pub fn get_move(&self) -> &'static MoveData {
-		match self.state {
-			PlayerState::Idle(frame) => idle_data(frame).unwrap(),
-			PlayerState::FWalk(frame) => fwalk_data(frame).unwrap(),
-			PlayerState::BWalk(frame) => bwalk_data(frame).unwrap(),
-			PlayerState::FDash(frame) => fdash_data(frame).unwrap(),
-			PlayerState::BDash(frame) => bdash_data(frame).unwrap(),
-			PlayerState::HBlock(frame, _) => hblock_data(frame).unwrap(),
-			PlayerState::LBlock(frame, _) => lblock_data(frame).unwrap(),
-			PlayerState::GuardBreak(frame) => guard_break_data(frame).unwrap(),
-			PlayerState::Hit(frame) => hit_data(frame).unwrap(),
-			PlayerState::NNormal(frame, _) => nnormal_data(frame).unwrap(),
-			PlayerState::MNormal(frame, _) => mnormal_data(frame).unwrap(),
-			PlayerState::NSpecial(frame, _) => nspecial_data(frame).unwrap(),
-			PlayerState::MSpecial(frame, _) => mspecial_data(frame).unwrap(),
-			PlayerState::Dead(_) => dead_data(),
+		Self::move_data(self.state).unwrap()
+	}
+
+	/// `None` if the move has no such frame.
+	pub fn move_data(state: PlayerState) -> Option<&'static MoveData> {
+		match state {
+			PlayerState::Idle(frame) => idle_data(frame),
+			PlayerState::FWalk(frame) => fwalk_data(frame),
+			PlayerState::BWalk(frame) => bwalk_data(frame),
+			PlayerState::FDash(frame) => fdash_data(frame),
+			PlayerState::BDash(frame) => bdash_data(frame),
+			PlayerState::HBlock(frame, _) => hblock_data(frame),
+			PlayerState::LBlock(frame, _) => lblock_data(frame),
+			PlayerState::GuardBreak(frame) => guard_break_data(frame),
+			PlayerState::Hit(frame) => hit_data(frame),
+			PlayerState::NNormal(frame, _) => nnormal_data(frame),
+			PlayerState::MNormal(frame, _) => mnormal_data(frame),
+			PlayerState::NSpecial(frame, _) => nspecial_data(frame),
+			PlayerState::MSpecial(frame, _) => mspecial_data(frame),
+			PlayerState::Dead(_) => Some(dead_data()),
 		}
 	}
 
+	/// The state must exist, see `move_data`.
+	#[inline]
+	pub const fn set_state(&mut self, state: PlayerState) {
+		self.state = state;
+	}
+
+	/// Frames attack has been held, the special is ready after the charge time.
+	#[inline]
+	pub const fn set_hold_time(&mut self, frames: u8) {
+		self.input_hold = frames;
+	}
+
 	pub fn get_attacked(&mut self, ender: bool, low: bool) {
 		let blockable_state = self.can_block();
+		let blocks = blockable_state && self.movement < 0;
+
+		if ender && !blocks {
+			self.enders_taken = self.enders_taken.saturating_add(1);
+		}
+		let ko = self.enders_taken >= self.handicap.enders_to_ko;
 
-		self.state = match blockable_state && self.movement < 0 {
+		self.state = match blocks {
 			true if self.guard_points == 0 => PlayerState::GuardBreak(0),
 			true if low => PlayerState::LBlock(0, ender),
 			true => PlayerState::HBlock(0, ender),
-			false if ender => PlayerState::Dead(false),
+			false if ender && ko => PlayerState::Dead(false),
 			false if !blockable_state => {
 				self.counter_hit = true;
 				PlayerState::Hit(0)
@@ -383,6 +441,7 @@ impl Player {
 		}
 	}
 
+	#[allow(unused)]
 	pub fn buff_time(&self) -> u8 {
 		match self.normal_buff {
 			Some(input) => input.buff_time.get(),
@@ -390,6 +449,45 @@ impl Player {
 		}
 	}
 
+	#[inline]
+	pub const fn is_attacking(&self) -> bool {
+		matches!(
+			self.state,
+			PlayerState::NNormal(_, _)
+				| PlayerState::MNormal(_, _)
+				| PlayerState::NSpecial(_, _)
+				| PlayerState::MSpecial(_, _)
+		)
+	}
+
+	#[inline]
+	pub const fn refill_guard(&mut self) {
+		self.guard_points = self.handicap.max_guard;
+	}
+
+	/// `speed` of the current move, walking speed scaled by `walk_speed` percent.
+	#[inline]
+	pub const fn speed(&self, speed: i16, walk_speed: u8) -> i16 {
+		match self.state {
+			PlayerState::FWalk(_) | PlayerState::BWalk(_) => {
+				(speed as i32 * walk_speed as i32 / 100) as i16
+			}
+			_ => speed,
+		}
+	}
+
+	#[inline]
+	pub const fn handicap(&self) -> Handicap {
+		self.handicap
+	}
+
+	/// Guard points are set to the starting guard right away.
+	#[inline]
+	pub const fn set_handicap(&mut self, handicap: Handicap) {
+		self.handicap = handicap;
+		self.guard_points = handicap.start_guard;
+	}
+
 	#[inline]
 	pub const fn is_dead(&self) -> bool {
 		matches!(self.state, PlayerState::Dead(_))
@@ -470,6 +568,11 @@ impl Player {
 		)
 	}
 
+	#[inline]
+	pub const fn state(&self) -> PlayerState {
+		self.state
+	}
+
 	#[inline]
 	pub fn state_int(&self) -> i64 {
 		self.state.into()
@@ -503,16 +606,84 @@ impl Player {
 		}
 	}
 
+	#[inline]
+	pub const fn set_input_config(&mut self, input_config: InputConfig) {
+		self.input_config = input_config;
+	}
+
+	#[inline]
+	pub const fn input_config(&self) -> InputConfig {
+		self.input_config
+	}
+
+	// Deserialized configs are not clamped
+	#[inline]
+	const fn buffer_time(&self) -> NonZeroU8 {
+		match NonZeroU8::new(self.input_config.buffer_time) {
+			Some(time) => time,
+			None => NonZeroU8::MIN,
+		}
+	}
+
 	#[inline]
 	const fn dash_time(&self) -> u8 {
-		match self.bot {
-			true => Self::BOT_DASH_TIME,
-			false => Self::PLAYER_DASH_TIME,
+		if self.input_config.dash_time > 0 {
+			self.input_config.dash_time
+		} else {
+			1
+		}
+	}
+
+	#[inline]
+	const fn charge_time(&self) -> u8 {
+		if self.input_config.charge_time > 0 {
+			self.input_config.charge_time
+		} else {
+			1
+		}
+	}
+}
+
+/// Modifiers to even out players of different strength. The charge time of specials is part of
+/// `InputConfig`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, GodotClass, Serialize, Deserialize)]
+#[class(no_init)]
+pub struct Handicap {
+	/// Guard points at the start of every round
+	pub start_guard: u8,
+	/// Guard points after a refill, in training mode
+	pub max_guard: u8,
+	/// Walking speed in percent
+	pub walk_speed: u8,
+	/// Unblocked enders needed in a round to KO, at least 1
+	pub enders_to_ko: u8,
+}
+
+#[godot_api]
+impl Handicap {
+	pub const NONE: Self = Self::new(3, 3, 100, 1);
+
+	#[func]
+	pub fn gd_new(start_guard: u8, max_guard: u8, walk_speed: u8, enders_to_ko: u8) -> Gd<Self> {
+		Gd::from_object(Self::new(start_guard, max_guard, walk_speed, enders_to_ko))
+	}
+
+	#[func]
+	pub fn gd_none() -> Gd<Self> {
+		Gd::from_object(Self::NONE)
+	}
+
+	pub const fn new(start_guard: u8, max_guard: u8, walk_speed: u8, enders_to_ko: u8) -> Self {
+		Handicap {
+			start_guard,
+			max_guard,
+			walk_speed,
+			enders_to_ko: if enders_to_ko > 0 { enders_to_ko } else { 1 },
 		}
 	}
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum PlayerState {
 	Idle(u8),
 	FWalk(u8),
@@ -555,10 +726,10 @@ impl PlayerState {
 	}
 }
 
-impl Into<i64> for PlayerState {
+impl From<PlayerState> for i64 {
 	#[inline]
-	fn into(self) -> i64 {
-		match self {
+	fn from(state: PlayerState) -> i64 {
+		match state {
 			PlayerState::Idle(_) => 0,
 			PlayerState::FWalk(_) => 1,
 			PlayerState::BWalk(_) => 2,
@@ -575,3 +746,65 @@ impl Into<i64> for PlayerState {
 		}
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::Handicap;
+	use crate::{input::{FgInput, InputConfig}, simul::Match};
+
+	const NONE: FgInput = FgInput::new(0, 0, false, false);
+	const ATTACK: FgInput = FgInput::new(0, 0, true, false);
+	const FORWARD: FgInput = FgInput::new(1, 0, false, false);
+
+	fn active(handicap: Handicap) -> Match {
+		let mut sim = Match::new(InputConfig::human(), InputConfig::human());
+		sim.player_mut(false).set_handicap(handicap);
+		while !sim.is_active() {
+			sim.step(NONE, NONE);
+		}
+		sim
+	}
+
+	#[test]
+	fn enders_to_ko() {
+		let mut sim = active(Handicap::new(3, 3, 100, 2));
+
+		for enders in 1..=2 {
+			while !sim.player(true).can_attack() || !sim.player(false).can_attack() {
+				sim.step(NONE, NONE);
+			}
+			sim.player_mut(true).position = Match::STAGE_LEN / 2 - 125;
+			sim.player_mut(false).position = Match::STAGE_LEN / 2 + 125;
+
+			// Normal cancelled into the special, which is an ender
+			sim.step(ATTACK, NONE);
+			while sim.player_state(false) == 0 {
+				sim.step(NONE, NONE);
+			}
+			sim.step(ATTACK, NONE);
+			for _ in 0..60 {
+				sim.step(NONE, NONE);
+			}
+
+			assert_eq!(sim.player(false).enders_taken, enders);
+			assert_eq!(sim.player(false).is_dead(), enders == 2);
+		}
+	}
+
+	#[test]
+	fn walk_speed() {
+		let walked = |walk_speed| {
+			let mut sim = active(Handicap::new(3, 3, walk_speed, 1));
+			let start = sim.p2_pos();
+			for _ in 0..30 {
+				sim.step(NONE, FORWARD);
+			}
+			start - sim.p2_pos()
+		};
+
+		let full = walked(100);
+		assert!(full > 0);
+		assert!((walked(50) - full / 2).abs() <= 30);
+		assert_eq!(walked(0), 0);
+	}
+}
@@ -0,0 +1,256 @@
+use std::mem;
+
+use godot::prelude::*;
+
+use crate::{
+	dataset::MacroAction,
+	input::FgInput,
+	player::{Player, PlayerState},
+};
+
+/// Frames labelled with one agent action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActionSegment {
+	pub action: MacroAction,
+	pub start: u32,
+	pub frames: u32,
+}
+
+impl ActionSegment {
+	pub fn to_dictionary(self) -> Dictionary {
+		let mut res = Dictionary::new();
+
+		res.set("action", self.action as i64);
+		res.set("name", self.action.name());
+		res.set("start", self.start);
+		res.set("frames", self.frames);
+
+		res
+	}
+}
+
+/// Splits a player's inputs into segments labelled with the agent's actions. Dashes, attacks and
+/// specials are read from the `Player` state machine, so they are labelled only when they
+/// actually came out, with the buffer, dash timers and charge already applied. Every other frame
+/// is labelled with the walk of its movement.
+#[derive(Debug, Clone)]
+pub struct ActionRecognizer {
+	segments: Vec<ActionSegment>,
+	frame: u32,
+	prev_state: PlayerState,
+	prev_hold: u8,
+	/// The current segment was started by an action, so it is not merged with walks
+	started: bool,
+	/// Attack has been held for the whole current segment
+	held: bool,
+	/// Frame the walks since the last action started on
+	walk_since: u32,
+}
+
+impl ActionRecognizer {
+	pub const fn new() -> Self {
+		ActionRecognizer {
+			segments: Vec::new(),
+			frame: 0,
+			prev_state: PlayerState::Idle(0),
+			prev_hold: 0,
+			started: false,
+			held: false,
+			walk_since: 0,
+		}
+	}
+
+	/// Must be called after every frame, with the player's input of the frame.
+	pub fn update(&mut self, player: &Player, input: FgInput) {
+		let state = player.state();
+		let walk = match input.movement {
+			1.. => MacroAction::FWalk,
+			..=-1 => MacroAction::BWalk,
+			0 => MacroAction::None,
+		};
+		let (attack, release) = match input.movement {
+			0 => (MacroAction::NAttack, MacroAction::NRelease),
+			_ => (MacroAction::MAttack, MacroAction::MRelease),
+		};
+
+		let started = match (self.prev_state, state) {
+			(prev, state) if prev == state => None,
+			(_, PlayerState::FDash(0)) => Some(MacroAction::FDash),
+			(_, PlayerState::BDash(0)) => Some(MacroAction::BDash),
+			(_, PlayerState::NNormal(0, _)) => Some(MacroAction::NAttack),
+			(_, PlayerState::MNormal(0, _)) => Some(MacroAction::MAttack),
+			// Cancels are a new press
+			(
+				PlayerState::NNormal(..) | PlayerState::MNormal(..),
+				PlayerState::NSpecial(0, _),
+			) => Some(attack),
+			(_, PlayerState::NSpecial(0, _)) => Some(MacroAction::NRelease),
+			(_, PlayerState::MSpecial(0, _)) => Some(MacroAction::MRelease),
+			_ => None,
+		};
+		let same_move = self.started
+			&& mem::discriminant(&state) == mem::discriminant(&self.prev_state)
+			&& matches!(
+				state,
+				PlayerState::FDash(_)
+					| PlayerState::BDash(_) | PlayerState::NNormal(..)
+					| PlayerState::MNormal(..) | PlayerState::NSpecial(..)
+					| PlayerState::MSpecial(..)
+			);
+		let released = self.prev_hold > 0 && player.hold_time() == 0;
+
+		match started {
+			Some(action @ (MacroAction::FDash | MacroAction::BDash)) => {
+				self.start_dash(action, player.input_config().dash_time, input)
+			}
+			Some(action) => self.start(action, true, input),
+			None if same_move => self.extend(input),
+			// Released without a charge
+			None if released => self.start(release, true, input),
+			None => match self.segments.last() {
+				Some(last) if !self.started && last.action == walk => {
+					self.extend(input)
+				}
+				_ => self.start(walk, false, input),
+			},
+		}
+
+		self.frame += 1;
+		self.prev_state = state;
+		self.prev_hold = player.hold_time();
+	}
+
+	/// Every segment so far, oldest first. The last one may still grow.
+	pub fn segments(&self) -> Vec<ActionSegment> {
+		let mut res = self.segments.clone();
+		if let Some(last) = res.last_mut() {
+			last.action = Self::hold_action(last.action, self.started && self.held);
+		}
+
+		res
+	}
+
+	/// The taps before the dash belong to it, as far back as the dash time.
+	fn start_dash(&mut self, action: MacroAction, dash_time: u8, input: FgInput) {
+		let since = match self.started {
+			true => self.frame,
+			false => self
+				.walk_since
+				.max(self.frame.saturating_sub(dash_time as u32)),
+		};
+		let mut start = self.frame;
+		while let Some(last) = self.segments.last().filter(|last| last.start >= since) {
+			start = last.start;
+			self.segments.pop();
+		}
+
+		self.start(action, true, input);
+		if let Some(last) = self.segments.last_mut() {
+			last.frames += last.start - start;
+			last.start = start;
+		}
+	}
+
+	fn start(&mut self, action: MacroAction, started: bool, input: FgInput) {
+		let held = self.started && self.held;
+		if let Some(last) = self.segments.last_mut() {
+			last.action = Self::hold_action(last.action, held);
+		}
+
+		self.segments.push(ActionSegment {
+			action,
+			start: self.frame,
+			frames: 1,
+		});
+		if self.started && !started {
+			self.walk_since = self.frame;
+		}
+		self.started = started;
+		self.held = input.attack_hold;
+	}
+
+	#[inline]
+	fn extend(&mut self, input: FgInput) {
+		if let Some(last) = self.segments.last_mut() {
+			last.frames += 1;
+		}
+		self.held &= input.attack_hold;
+	}
+
+	/// Attacks held through their whole segment are holds.
+	#[inline]
+	const fn hold_action(action: MacroAction, held: bool) -> MacroAction {
+		match (action, held) {
+			(MacroAction::NAttack, true) => MacroAction::NHold,
+			(MacroAction::MAttack, true) => MacroAction::MHold,
+			(action, _) => action,
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{
+		dataset::MacroAction,
+		decoder::{InputDecoder, RawInput, SocdPolicy},
+		input::{FgInput, InputConfig},
+		replay::Replay,
+	};
+
+	const NO_INPUT: FgInput = FgInput::new(0, 0, false, false);
+	const NONE: RawInput = RawInput::new(false, false, false);
+	const FORWARD: RawInput = RawInput::new(true, false, false);
+	const ATTACK: RawInput = RawInput::new(false, false, true);
+
+	#[test]
+	fn segments() {
+		let raw: Vec<RawInput> = [
+			// Round start
+			(NONE, 100),
+			(FORWARD, 10),
+			(NONE, 30),
+			// Dash
+			(FORWARD, 1),
+			(NONE, 1),
+			(FORWARD, 1),
+			(NONE, 40),
+			// Attack
+			(ATTACK, 1),
+			(NONE, 60),
+			// Hold until charged, then release
+			(ATTACK, 120),
+			(NONE, 120),
+		]
+		.into_iter()
+		.flat_map(|(raw, frames)| std::iter::repeat_n(raw, frames))
+		.collect();
+
+		let mut decoder = InputDecoder::new(SocdPolicy::Neutral);
+		let mut replay = Replay::new(InputConfig::human(), InputConfig::human());
+		for raw in raw {
+			replay.push(decoder.decode(raw), NO_INPUT);
+		}
+
+		let segments = replay.recognize(true);
+		assert_eq!(
+			segments.iter().map(|s| s.frames).sum::<u32>(),
+			replay.frames() as u32
+		);
+		assert_eq!(
+			segments.iter().map(|s| s.action).collect::<Vec<_>>(),
+			[
+				MacroAction::None,
+				MacroAction::FWalk,
+				MacroAction::None,
+				MacroAction::FDash,
+				MacroAction::None,
+				MacroAction::NAttack,
+				MacroAction::None,
+				MacroAction::NHold,
+				MacroAction::None,
+				MacroAction::NRelease,
+				MacroAction::None,
+			]
+		);
+	}
+}
@@ -0,0 +1,219 @@
+use crate::{input::FgInput, rng::Rng};
+
+/// `FgInput` directions are relative to where the player faces, and the players always face each
+/// other. So a recording made on either side already plays back mirrored on the other side.
+#[derive(Debug, Clone, Hash)]
+pub struct InputRecorder {
+	slots: [Vec<FgInput>; Self::SLOTS],
+	enabled: [bool; Self::SLOTS],
+	max_frames: usize,
+	record_slot: usize,
+	/// Slot and frame being played
+	playing: Option<(usize, usize)>,
+	pub looping: bool,
+}
+
+impl InputRecorder {
+	pub const SLOTS: usize = 5;
+	/// 10 seconds
+	pub const DEFAULT_MAX_FRAMES: usize = 600;
+
+	pub const fn new(max_frames: usize) -> Self {
+		InputRecorder {
+			slots: [const { Vec::new() }; Self::SLOTS],
+			enabled: [true; Self::SLOTS],
+			max_frames,
+			record_slot: 0,
+			playing: None,
+			looping: true,
+		}
+	}
+
+	/// Clears `slot` and records into it from now on. Out of range slots are ignored.
+	pub fn start_recording(&mut self, slot: usize) {
+		if slot >= Self::SLOTS {
+			return;
+		}
+
+		self.record_slot = slot;
+		self.slots[slot].clear();
+		self.playing = None;
+	}
+
+	/// Inputs past the frame limit are dropped.
+	#[inline]
+	pub fn record(&mut self, input: FgInput) {
+		let slot = &mut self.slots[self.record_slot];
+
+		if slot.len() < self.max_frames {
+			slot.push(input);
+		}
+	}
+
+	/// Playback stops if it was past the new limit.
+	pub fn set_max_frames(&mut self, frames: usize) {
+		self.max_frames = frames;
+
+		for slot in self.slots.iter_mut() {
+			slot.truncate(frames);
+		}
+
+		if self.playing.is_some_and(|(_, frame)| frame >= frames) {
+			self.playing = None;
+		}
+	}
+
+	/// Playback stops if it was playing `slot`.
+	pub fn clear(&mut self, slot: usize) {
+		if let Some(inputs) = self.slots.get_mut(slot) {
+			inputs.clear();
+		}
+
+		if self.playing.is_some_and(|(playing, _)| playing == slot) {
+			self.playing = None;
+		}
+	}
+
+	/// Disabled slots are not picked for playback.
+	pub fn set_enabled(&mut self, slot: usize, enabled: bool) {
+		if let Some(slot) = self.enabled.get_mut(slot) {
+			*slot = enabled;
+		}
+	}
+
+	#[inline]
+	pub fn slot_len(&self, slot: usize) -> usize {
+		self.slots.get(slot).map_or(0, Vec::len)
+	}
+
+	/// Picks a random enabled and non-empty slot, playback stops if there is none.
+	pub fn start_playback(&mut self, rng: &mut Rng) {
+		let mut candidates = [0; Self::SLOTS];
+		let mut count = 0;
+
+		for slot in 0..Self::SLOTS {
+			if self.enabled[slot] && !self.slots[slot].is_empty() {
+				candidates[count] = slot;
+				count += 1;
+			}
+		}
+
+		self.playing = match count {
+			0 => None,
+			_ => Some((candidates[(rng.next_u64() % count as u64) as usize], 0)),
+		};
+	}
+
+	/// Next input of the playback, `None` once it is over. With looping on, a new slot is
+	/// picked every time the current one ends.
+	pub fn next_input(&mut self, rng: &mut Rng) -> Option<FgInput> {
+		let (slot, frame) = self.playing?;
+
+		let Some(&input) = self.slots[slot].get(frame) else {
+			self.playing = None;
+			return None;
+		};
+
+		if frame + 1 < self.slots[slot].len() {
+			self.playing = Some((slot, frame + 1));
+		} else if self.looping {
+			self.start_playback(rng);
+		} else {
+			self.playing = None;
+		}
+
+		Some(input)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::InputRecorder;
+	use crate::{input::FgInput, rng::Rng};
+
+	const FORWARD: FgInput = FgInput::new(1, 0, false, false);
+	const BACKWARD: FgInput = FgInput::new(-1, 0, false, false);
+
+	#[test]
+	fn record_limit() {
+		let mut recorder = InputRecorder::new(3);
+		recorder.start_recording(1);
+		for _ in 0..5 {
+			recorder.record(FORWARD);
+		}
+
+		assert_eq!(recorder.slot_len(0), 0);
+		assert_eq!(recorder.slot_len(1), 3);
+
+		recorder.set_max_frames(2);
+		assert_eq!(recorder.slot_len(1), 2);
+	}
+
+	#[test]
+	fn playback() {
+		let mut rng = Rng::new(0);
+		let mut recorder = InputRecorder::new(10);
+		recorder.start_recording(0);
+		recorder.record(FORWARD);
+		recorder.record(BACKWARD);
+
+		recorder.looping = false;
+		recorder.start_playback(&mut rng);
+		assert_eq!(recorder.next_input(&mut rng), Some(FORWARD));
+		assert_eq!(recorder.next_input(&mut rng), Some(BACKWARD));
+		assert_eq!(recorder.next_input(&mut rng), None);
+
+		recorder.looping = true;
+		recorder.start_playback(&mut rng);
+		for _ in 0..3 {
+			assert_eq!(recorder.next_input(&mut rng), Some(FORWARD));
+			assert_eq!(recorder.next_input(&mut rng), Some(BACKWARD));
+		}
+	}
+
+	#[test]
+	fn clear_while_playing() {
+		let mut rng = Rng::new(0);
+		let mut recorder = InputRecorder::new(10);
+		recorder.start_recording(0);
+		recorder.record(FORWARD);
+		recorder.record(BACKWARD);
+		recorder.record(FORWARD);
+
+		recorder.start_playback(&mut rng);
+		assert_eq!(recorder.next_input(&mut rng), Some(FORWARD));
+		assert_eq!(recorder.next_input(&mut rng), Some(BACKWARD));
+		recorder.set_max_frames(2);
+		assert_eq!(recorder.next_input(&mut rng), None);
+
+		recorder.start_playback(&mut rng);
+		assert_eq!(recorder.next_input(&mut rng), Some(FORWARD));
+		recorder.clear(0);
+		assert_eq!(recorder.next_input(&mut rng), None);
+	}
+
+	#[test]
+	fn random_slots() {
+		let mut rng = Rng::new(0);
+		let mut recorder = InputRecorder::new(10);
+		recorder.start_recording(0);
+		recorder.record(FORWARD);
+		recorder.start_recording(3);
+		recorder.record(BACKWARD);
+
+		recorder.start_playback(&mut rng);
+		let picks = (0..100)
+			.map(|_| recorder.next_input(&mut rng).unwrap())
+			.collect::<Vec<_>>();
+		assert!(picks.contains(&FORWARD));
+		assert!(picks.contains(&BACKWARD));
+
+		recorder.set_enabled(0, false);
+		recorder.start_playback(&mut rng);
+		assert!((0..100).all(|_| recorder.next_input(&mut rng) == Some(BACKWARD)));
+
+		recorder.set_enabled(3, false);
+		recorder.start_playback(&mut rng);
+		assert_eq!(recorder.next_input(&mut rng), None);
+	}
+}
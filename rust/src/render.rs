@@ -0,0 +1,542 @@
+use std::{
+	collections::HashMap,
+	fs::File,
+	io::{self, BufWriter, Write},
+	path::Path,
+};
+
+use crate::{framedata::WorldBox, replay::Replay, simul::Match};
+
+pub type Color = [u8; 4];
+
+/// RGBA image, row major.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Canvas {
+	pub width: usize,
+	pub height: usize,
+	pub pixels: Vec<u8>,
+}
+
+impl Canvas {
+	pub fn new(width: usize, height: usize) -> Self {
+		Canvas {
+			width,
+			height,
+			pixels: vec![0; width * height * 4],
+		}
+	}
+
+	pub fn clear(&mut self, color: Color) {
+		for pixel in self.pixels.chunks_exact_mut(4) {
+			pixel.copy_from_slice(&color);
+		}
+	}
+
+	#[cfg(test)]
+	#[inline]
+	pub fn pixel(&self, x: usize, y: usize) -> Color {
+		let i = (y * self.width + x) * 4;
+		[
+			self.pixels[i],
+			self.pixels[i + 1],
+			self.pixels[i + 2],
+			self.pixels[i + 3],
+		]
+	}
+
+	/// Alpha blends `color` over the pixel, out of bounds pixels are ignored.
+	#[inline]
+	pub fn blend(&mut self, x: i32, y: i32, color: Color) {
+		if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+			return;
+		}
+
+		let i = (y as usize * self.width + x as usize) * 4;
+		let alpha = color[3] as u32;
+
+		for (dst, src) in self.pixels[i..i + 3].iter_mut().zip(color) {
+			*dst = ((src as u32 * alpha + *dst as u32 * (255 - alpha)) / 255) as u8;
+		}
+		self.pixels[i + 3] = self.pixels[i + 3].max(color[3]);
+	}
+
+	/// Fills `[x1, x2) x [y1, y2)`
+	pub fn fill_rect(&mut self, x1: i32, y1: i32, x2: i32, y2: i32, color: Color) {
+		let (x1, x2) = (x1.max(0), x2.min(self.width as i32));
+		let (y1, y2) = (y1.max(0), y2.min(self.height as i32));
+
+		for y in y1..y2 {
+			for x in x1..x2 {
+				self.blend(x, y, color);
+			}
+		}
+	}
+
+	/// Draws `sprite` with its top left corner at `(x, y)`, each sprite pixel is `scale` pixels.
+	pub fn blit(&mut self, sprite: &Sprite, x: i32, y: i32, scale: f32, flip: bool) {
+		let width = (sprite.width as f32 * scale) as i32;
+		let height = (sprite.height as f32 * scale) as i32;
+
+		for dy in 0..height {
+			let sy = ((dy as f32 / scale) as usize).min(sprite.height - 1);
+
+			for dx in 0..width {
+				let sx = ((dx as f32 / scale) as usize).min(sprite.width - 1);
+				let sx = if flip { sprite.width - 1 - sx } else { sx };
+
+				let i = (sy * sprite.width + sx) * 4;
+				let color = [
+					sprite.pixels[i],
+					sprite.pixels[i + 1],
+					sprite.pixels[i + 2],
+					sprite.pixels[i + 3],
+				];
+
+				if color[3] != 0 {
+					self.blend(x + dx, y + dy, color);
+				}
+			}
+		}
+	}
+
+	pub fn write_png(&self, writer: impl Write) -> io::Result<()> {
+		let mut encoder = png::Encoder::new(writer, self.width as u32, self.height as u32);
+		encoder.set_color(png::ColorType::Rgba);
+		encoder.set_depth(png::BitDepth::Eight);
+		encoder.write_header()?.write_image_data(&self.pixels)?;
+
+		Ok(())
+	}
+}
+
+/// RGBA image, row major.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sprite {
+	pub width: usize,
+	pub height: usize,
+	pub pixels: Vec<u8>,
+}
+
+impl Sprite {
+	pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+		let mut decoder = png::Decoder::new(File::open(path)?);
+		decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+		let mut reader = decoder.read_info()?;
+		let mut buf = vec![0; reader.output_buffer_size()];
+		let info = reader.next_frame(&mut buf)?;
+		let buf = &buf[..info.buffer_size()];
+
+		let pixels = match info.color_type {
+			png::ColorType::Grayscale => {
+				buf.iter().flat_map(|&g| [g, g, g, 255]).collect()
+			}
+			png::ColorType::GrayscaleAlpha => buf
+				.chunks_exact(2)
+				.flat_map(|p| [p[0], p[0], p[0], p[1]])
+				.collect(),
+			png::ColorType::Rgb => buf
+				.chunks_exact(3)
+				.flat_map(|p| [p[0], p[1], p[2], 255])
+				.collect(),
+			png::ColorType::Rgba => buf.to_vec(),
+			// normalize_to_color8 expands palettes
+			png::ColorType::Indexed => unreachable!(),
+		};
+
+		Ok(Sprite {
+			width: info.width as usize,
+			height: info.height as usize,
+			pixels,
+		})
+	}
+}
+
+/// Sprites by their animation frame name, like `godot/art/fighter`.
+#[derive(Debug, Clone, Default)]
+pub struct SpriteSheet {
+	sprites: HashMap<String, Sprite>,
+}
+
+impl SpriteSheet {
+	pub fn load_dir(dir: impl AsRef<Path>) -> io::Result<Self> {
+		let mut sprites = HashMap::new();
+
+		for entry in std::fs::read_dir(dir)? {
+			let path = entry?.path();
+
+			if path.extension().is_some_and(|ext| ext == "png") {
+				let Some(name) = path.file_stem().and_then(|x| x.to_str()) else {
+					continue;
+				};
+				sprites.insert(name.to_string(), Sprite::load(&path)?);
+			}
+		}
+
+		Ok(SpriteSheet { sprites })
+	}
+
+	#[inline]
+	pub fn get(&self, name: &str) -> Option<&Sprite> {
+		self.sprites.get(name)
+	}
+}
+
+/// Draws matches without Godot, players are drawn as boxes unless sprites are given.
+#[derive(Debug, Clone)]
+pub struct Renderer {
+	pub width: usize,
+	pub height: usize,
+	pub sprites: Option<SpriteSheet>,
+	pub draw_boxes: bool,
+}
+
+impl Renderer {
+	// Same view of the stage `battle_scene.gd` has
+	const VIEW_START: i16 = -200;
+	const VIEW_LEN: f32 = 1872.0;
+	/// Stage units per sprite pixel in `battle_scene.tscn`
+	const SPRITE_SCALE: f32 = 12.45;
+	/// Where the player's position is in the sprite, from its left edge
+	const SPRITE_ANCHOR: f32 = 200.0;
+
+	const BACKGROUND: Color = [32, 32, 40, 255];
+	const FLOOR: Color = [90, 90, 100, 255];
+	const BODY: [Color; 2] = [[220, 220, 220, 255], [200, 80, 80, 255]];
+	const COLLISION: Color = [120, 120, 120, 160];
+	const HURTBOX: Color = [60, 90, 230, 130];
+	const HITBOX: Color = [230, 50, 50, 160];
+	const GUARD: Color = [240, 200, 60, 255];
+	const ROUND: Color = [240, 240, 240, 255];
+	const EMPTY: Color = [70, 70, 80, 255];
+
+	/// Largest width and height, GIFs store them in 16 bits
+	pub const MAX_SIZE: usize = u16::MAX as usize;
+	/// Shortest GIF frame delay browsers respect, shorter ones are shown as 1/10 seconds
+	const MIN_GIF_DELAY: usize = 2;
+
+	pub const fn new(width: usize, height: usize) -> Self {
+		Renderer {
+			width,
+			height,
+			sprites: None,
+			draw_boxes: true,
+		}
+	}
+
+	/// Sprites are loaded from `sprite_dir`, players are drawn as boxes if it is empty. Sizes
+	/// must be from 1 to `MAX_SIZE`.
+	pub fn with_sprites(width: usize, height: usize, sprite_dir: &str) -> io::Result<Self> {
+		let valid = 1..=Self::MAX_SIZE;
+		if !valid.contains(&width) || !valid.contains(&height) {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				format!(
+					"render size {width}x{height} is not from 1 to {}",
+					Self::MAX_SIZE
+				),
+			));
+		}
+
+		let mut renderer = Self::new(width, height);
+
+		if !sprite_dir.is_empty() {
+			renderer.sprites = Some(SpriteSheet::load_dir(sprite_dir)?);
+		}
+
+		Ok(renderer)
+	}
+
+	pub fn render(&self, sim: &Match, canvas: &mut Canvas) {
+		canvas.clear(Self::BACKGROUND);
+
+		let floor = self.floor();
+		canvas.fill_rect(0, floor, self.width as i32, self.height as i32, Self::FLOOR);
+
+		for p1 in [true, false] {
+			self.draw_player(sim, canvas, p1);
+		}
+
+		if self.draw_boxes {
+			for p1 in [true, false] {
+				self.draw_box(canvas, sim.collision_box(p1), Self::COLLISION);
+				for hurtbox in sim.hurtboxes(p1) {
+					self.draw_box(canvas, hurtbox, Self::HURTBOX);
+				}
+				if let Some(hitbox) = sim.hitbox(p1) {
+					self.draw_box(canvas, hitbox, Self::HITBOX);
+				}
+			}
+		}
+
+		self.draw_ui(sim, canvas);
+	}
+
+	/// Writes every `every`th frame as `frame_00000.png` into `dir`, returns the frame count.
+	pub fn render_replay_pngs(
+		&self,
+		replay: &Replay,
+		dir: impl AsRef<Path>,
+		every: usize,
+	) -> io::Result<usize> {
+		let dir = dir.as_ref();
+		let mut canvas = Canvas::new(self.width, self.height);
+		let mut written = 0;
+		let mut res = Ok(());
+
+		self.for_replay_frames(replay, every, |sim| {
+			if res.is_err() {
+				return;
+			}
+
+			self.render(sim, &mut canvas);
+			res = File::create(dir.join(format!("frame_{written:05}.png")))
+				.and_then(|file| canvas.write_png(BufWriter::new(file)));
+			written += 1;
+		});
+
+		res.map(|_| written)
+	}
+
+	/// Encodes every `every`th frame as an animated GIF. Browsers show frames shorter than
+	/// `MIN_GIF_DELAY` slower, so frames that would be shown shorter are dropped instead, and the
+	/// clip still plays in real time.
+	pub fn render_replay_gif(
+		&self,
+		replay: &Replay,
+		writer: impl Write,
+		every: usize,
+	) -> io::Result<()> {
+		let mut encoder =
+			gif::Encoder::new(writer, self.width as u16, self.height as u16, &[])
+				.map_err(io::Error::other)?;
+		encoder.set_repeat(gif::Repeat::Infinite)
+			.map_err(io::Error::other)?;
+
+		let every = every.max(1);
+		let mut canvas = Canvas::new(self.width, self.height);
+		// Start time of the frame waiting for its delay, and its pixels
+		let mut pending: Option<(usize, Vec<u8>)> = None;
+		let mut frame = 0;
+		let mut res = Ok(());
+
+		self.for_replay_frames(replay, every, |sim| {
+			let time = Self::gif_time(frame * every);
+			frame += 1;
+			if res.is_err() {
+				return;
+			}
+
+			if let Some((start, pixels)) = &mut pending {
+				if time - *start < Self::MIN_GIF_DELAY {
+					return;
+				}
+				res = self.write_gif_frame(&mut encoder, pixels, time - *start);
+			}
+
+			self.render(sim, &mut canvas);
+			pending = Some((time, canvas.pixels.clone()));
+		});
+
+		match pending {
+			Some((_, mut pixels)) => res.and_then(|_| {
+				let delay = Self::gif_time(every).max(Self::MIN_GIF_DELAY);
+				self.write_gif_frame(&mut encoder, &mut pixels, delay)
+			}),
+			None => res,
+		}
+	}
+
+	/// In 1/100 seconds, the game runs at 60 FPS. Rounded down from the start of the replay, so
+	/// the rounding errors do not add up.
+	#[inline]
+	const fn gif_time(frames: usize) -> usize {
+		frames * 100 / 60
+	}
+
+	fn write_gif_frame(
+		&self,
+		encoder: &mut gif::Encoder<impl Write>,
+		pixels: &mut [u8],
+		delay: usize,
+	) -> io::Result<()> {
+		let mut frame = gif::Frame::from_rgba_speed(
+			self.width as u16,
+			self.height as u16,
+			pixels,
+			10,
+		);
+		frame.delay = delay.min(u16::MAX as usize) as u16;
+		encoder.write_frame(&frame).map_err(io::Error::other)
+	}
+
+	fn for_replay_frames(
+		&self,
+		replay: &Replay,
+		every: usize,
+		mut on_frame: impl FnMut(&Match),
+	) {
+		let mut frame = 0;
+
+		replay.play(|sim, _| {
+			if frame % every.max(1) == 0 {
+				on_frame(sim);
+			}
+			frame += 1;
+		});
+	}
+
+	fn draw_player(&self, sim: &Match, canvas: &mut Canvas, p1: bool) {
+		let position = sim.player(p1).position;
+		let sprite = self
+			.sprites
+			.as_ref()
+			.and_then(|sheet| sheet.get(sim.player(p1).get_move().animation_frame));
+
+		let Some(sprite) = sprite else {
+			let body = sim.collision_box(p1);
+			let (x1, y1) = self.to_screen(body.x1, body.y2);
+			let (x2, y2) = self.to_screen(body.x2, body.y1);
+			canvas.fill_rect(x1, y1, x2, y2, Self::BODY[!p1 as usize]);
+			return;
+		};
+
+		let scale = Self::SPRITE_SCALE * self.scale();
+		let width = sprite.width as f32 * scale;
+		let height = sprite.height as f32 * scale;
+		let anchor = Self::SPRITE_ANCHOR * self.scale();
+
+		let (x, _) = self.to_screen(position, 0);
+		let x = match p1 {
+			true => x as f32 - anchor,
+			false => x as f32 + anchor - width,
+		};
+
+		canvas.blit(sprite, x as i32, self.floor() - height as i32, scale, !p1);
+	}
+
+	fn draw_ui(&self, sim: &Match, canvas: &mut Canvas) {
+		let size = (self.height / 24).max(2) as i32;
+		let gap = size / 2;
+
+		for p1 in [true, false] {
+			let guard = sim.player_guard(p1) as i32;
+			let wins = if p1 { sim.p1_wins() } else { sim.p2_wins() } as i32;
+
+			for i in 0..3 {
+				let offset = gap + i * (size + gap);
+				let x = match p1 {
+					true => offset,
+					false => self.width as i32 - offset - size,
+				};
+
+				let color = if i < guard { Self::GUARD } else { Self::EMPTY };
+				canvas.fill_rect(x, gap, x + size, gap + size, color);
+
+				let color = if i < wins { Self::ROUND } else { Self::EMPTY };
+				let y = gap * 2 + size;
+				canvas.fill_rect(x, y, x + size, y + size, color);
+			}
+		}
+
+		// Round timer as a bar shrinking towards the center
+		let half = (self.width as f32 / 6.0) * (sim.timer_sec() as f32 / 60.0).min(1.0);
+		let center = self.width as i32 / 2;
+		canvas.fill_rect(
+			center - half as i32,
+			gap,
+			center + half as i32,
+			gap + size,
+			Self::ROUND,
+		);
+	}
+
+	fn draw_box(&self, canvas: &mut Canvas, wbox: WorldBox, color: Color) {
+		let (x1, y1) = self.to_screen(wbox.x1, wbox.y2);
+		let (x2, y2) = self.to_screen(wbox.x2, wbox.y1);
+		canvas.fill_rect(x1, y1, x2, y2, color);
+	}
+
+	#[inline]
+	fn scale(&self) -> f32 {
+		self.width as f32 / Self::VIEW_LEN
+	}
+
+	#[inline]
+	fn floor(&self) -> i32 {
+		(self.height as f32 * 0.85) as i32
+	}
+
+	#[inline]
+	fn to_screen(&self, x: i16, y: i16) -> (i32, i32) {
+		let sx = (x - Self::VIEW_START) as f32 * self.scale();
+		let sy = self.floor() as f32 - y as f32 * self.scale();
+
+		(sx as i32, sy as i32)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{Canvas, Renderer};
+	use crate::{
+		input::{FgInput, InputConfig},
+		replay::Replay,
+		simul::Match,
+	};
+
+	#[test]
+	fn boxes() {
+		let sim = Match::new(InputConfig::human(), InputConfig::human());
+		let renderer = Renderer::new(468, 200);
+		let mut canvas = Canvas::new(renderer.width, renderer.height);
+
+		renderer.render(&sim, &mut canvas);
+
+		// Player 1 stands at 400, which is (400 + 200) / 4 pixels in
+		let (x, y) = renderer.to_screen(sim.p1_pos(), 10);
+		assert_ne!(
+			canvas.pixel(x as usize + 5, y as usize),
+			Renderer::BACKGROUND
+		);
+		assert_eq!(
+			canvas.pixel(renderer.width / 2, y as usize),
+			Renderer::BACKGROUND
+		);
+	}
+
+	#[test]
+	fn gif_delays() {
+		let mut replay = Replay::new(InputConfig::human(), InputConfig::human());
+		let none = FgInput::new(0, 0, false, false);
+		for _ in 0..120 {
+			replay.push(none, none);
+		}
+
+		for every in [1, 2, 5] {
+			let mut gif = Vec::new();
+			Renderer::new(32, 16)
+				.render_replay_gif(&replay, &mut gif, every)
+				.unwrap();
+
+			let mut decoder =
+				gif::DecodeOptions::new().read_info(gif.as_slice()).unwrap();
+			let mut delays = Vec::new();
+			while let Some(frame) = decoder.read_next_frame().unwrap() {
+				delays.push(frame.delay);
+			}
+
+			// 2 seconds, give or take the last frame
+			assert!(delays.iter().all(|&delay| delay >= 2));
+			let total = delays.iter().map(|&delay| delay as usize).sum::<usize>();
+			assert!((198..=203).contains(&total));
+		}
+	}
+
+	#[test]
+	fn sizes() {
+		assert!(Renderer::with_sprites(468, 200, "").is_ok());
+		assert!(Renderer::with_sprites(0, 200, "").is_err());
+		assert!(Renderer::with_sprites(468, Renderer::MAX_SIZE + 1, "").is_err());
+	}
+}
@@ -0,0 +1,298 @@
+use std::{fs::File, io::BufWriter};
+
+use godot::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	domain::DomainRandomization,
+	history::HistoryEntry,
+	input::{FgInput, InputConfig},
+	player::Handicap,
+	recognizer::{ActionRecognizer, ActionSegment},
+	render::Renderer,
+	simul::{self, Match},
+};
+
+/// Inputs of every `Match::frame_update` call of a match. The simulator is deterministic, so
+/// replaying them reproduces the match exactly, as long as the replay has the settings of the
+/// match, see `copy_settings`. Training mode is not kept.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, GodotClass)]
+#[class(no_init)]
+pub struct Replay {
+	pub input_configs: [InputConfig; 2],
+	pub handicaps: [Handicap; 2],
+	/// Frames and jitter, see `Match::set_input_delay`
+	#[serde(default)]
+	pub input_delays: [Option<(u8, u8)>; 2],
+	/// See `Match::enable_domain_randomization`
+	#[serde(default)]
+	pub domain: Option<DomainRandomization>,
+	/// See `Match::set_seed`
+	pub seed: u64,
+	pub inputs: Vec<[FgInput; 2]>,
+}
+
+#[godot_api]
+impl Replay {
+	/// Bots and humans get the same input config presets as in `Match::gd_new`.
+	#[func]
+	pub fn gd_new(p1_bot: bool, p2_bot: bool) -> Gd<Self> {
+		Gd::from_object(Self::new(
+			InputConfig::preset(p1_bot),
+			InputConfig::preset(p2_bot),
+		))
+	}
+
+	#[func]
+	pub fn gd_with_input_configs(
+		config1: Gd<InputConfig>,
+		config2: Gd<InputConfig>,
+	) -> Gd<Self> {
+		Gd::from_object(Self::new(*config1.bind(), *config2.bind()))
+	}
+
+	pub const fn new(config1: InputConfig, config2: InputConfig) -> Self {
+		Replay {
+			input_configs: [config1, config2],
+			handicaps: [Handicap::NONE; 2],
+			input_delays: [None; 2],
+			domain: None,
+			seed: 0,
+			inputs: Vec::new(),
+		}
+	}
+
+	#[func]
+	pub fn gd_copy_settings(&mut self, sim: Gd<Match>) -> bool {
+		let copied = self.copy_settings(&sim.bind());
+		if !copied {
+			godot_error!("Replay: matches in training mode cannot be replayed");
+		}
+
+		copied
+	}
+
+	/// Takes the input configs, handicaps, input delays and domain randomisation of `sim`, which
+	/// should not have played a frame yet. The seed has to be set separately. Returns false and
+	/// leaves the replay as is if `sim` is in training mode, as the dummy is not replayable.
+	pub fn copy_settings(&mut self, sim: &Match) -> bool {
+		if sim.in_training() {
+			return false;
+		}
+
+		for (i, p1) in [true, false].into_iter().enumerate() {
+			self.input_configs[i] = sim.player(p1).input_config();
+			self.handicaps[i] = sim.player(p1).handicap();
+			self.input_delays[i] = sim.input_delay(p1);
+		}
+		self.domain = sim.domain_randomization().cloned();
+
+		true
+	}
+
+	#[func]
+	pub fn set_seed(&mut self, seed: i64) {
+		self.seed = seed as u64;
+	}
+
+	#[func]
+	pub fn set_handicap(&mut self, p1: bool, handicap: Gd<Handicap>) {
+		self.handicaps[!p1 as usize] = *handicap.bind();
+	}
+
+	#[func]
+	pub fn gd_push(&mut self, input1: Gd<FgInput>, input2: Gd<FgInput>) {
+		self.push(*input1.bind(), *input2.bind());
+	}
+
+	#[inline]
+	pub fn push(&mut self, input1: FgInput, input2: FgInput) {
+		self.inputs.push([input1, input2]);
+	}
+
+	#[func]
+	pub fn gd_mirrored(&self) -> Gd<Self> {
+		Gd::from_object(self.mirrored())
+	}
+
+	/// Same replay with the players swapped, see `Match::mirrored`.
+	pub fn mirrored(&self) -> Self {
+		let [config1, config2] = self.input_configs;
+		let [handicap1, handicap2] = self.handicaps;
+		let [delay1, delay2] = self.input_delays;
+
+		Replay {
+			input_configs: [config2, config1],
+			handicaps: [handicap2, handicap1],
+			input_delays: [delay2, delay1],
+			domain: self.domain.clone(),
+			seed: self.seed,
+			inputs: self
+				.inputs
+				.iter()
+				.map(|&[input1, input2]| [input2, input1])
+				.collect(),
+		}
+	}
+
+	#[func]
+	pub fn frames(&self) -> i64 {
+		self.inputs.len() as i64
+	}
+
+	/// Plays the match back, calling `on_frame` after every frame, and stops when the match is
+	/// over. A new round starts right after the frame that ended the last one, so replays hold
+	/// no inputs between rounds. `battle_scene.gd` instead spends the next frame and its inputs
+	/// on `Match::new_round`, so that frame must be left out when recording from it.
+	pub fn play(&self, mut on_frame: impl FnMut(&Match, simul::Result)) {
+		let mut sim = self.start();
+
+		for [input1, input2] in self.inputs.iter().copied() {
+			let res = sim.step(input1, input2);
+			on_frame(&sim, res);
+
+			if matches!(res, simul::Result::Continue | simul::Result::Pause) {
+				continue;
+			}
+
+			sim.new_round();
+			if !sim.continues() {
+				break;
+			}
+		}
+	}
+
+	/// The match before the first frame, with the settings and seed of the replay.
+	pub fn start(&self) -> Match {
+		let [config1, config2] = self.input_configs;
+		let mut sim = Match::new(config1, config2);
+		for (i, p1) in [true, false].into_iter().enumerate() {
+			sim.player_mut(p1).set_handicap(self.handicaps[i]);
+			if let Some((frames, jitter)) = self.input_delays[i] {
+				sim.set_input_delay(p1, frames, jitter);
+			}
+		}
+		sim.set_domain_randomization(self.domain.clone());
+		sim.set_seed(self.seed as i64);
+
+		sim
+	}
+
+	/// The player's inputs split into the agent's actions, in the format of
+	/// `ActionSegment::to_dictionary`.
+	#[func]
+	pub fn action_segments(&self, p1: bool) -> Array<Dictionary> {
+		self.recognize(p1)
+			.into_iter()
+			.map(ActionSegment::to_dictionary)
+			.collect()
+	}
+
+	pub fn recognize(&self, p1: bool) -> Vec<ActionSegment> {
+		let mut recognizer = ActionRecognizer::new();
+		let mut inputs = self.inputs.iter();
+		self.play(|sim, _| {
+			if let Some(&[input1, input2]) = inputs.next() {
+				recognizer.update(sim.player(p1), if p1 { input1 } else { input2 });
+			}
+		});
+
+		recognizer.segments()
+	}
+
+	/// Every input of the player in the replay, oldest first, in the format of
+	/// `Match::input_history`.
+	#[func]
+	pub fn input_history(&self, p1: bool) -> Array<Dictionary> {
+		self.history(p1).map(HistoryEntry::to_dictionary).collect()
+	}
+
+	/// Inputs of the player with repeats merged, oldest first.
+	pub fn history(&self, p1: bool) -> impl Iterator<Item = HistoryEntry> + '_ {
+		let mut inputs = self.inputs.iter().map(move |x| x[!p1 as usize]).peekable();
+
+		std::iter::from_fn(move || {
+			let mut entry = HistoryEntry {
+				input: inputs.next()?,
+				frames: 1,
+			};
+			while inputs.next_if(|&input| entry.merge(input)).is_some() {}
+
+			Some(entry)
+		})
+	}
+
+	/// Writes every `every`th frame into `dir` as PNGs, returns the frame count or -1 on
+	/// failure. Players are drawn as boxes if `sprite_dir` is empty.
+	#[func]
+	pub fn render_pngs(
+		&self,
+		dir: GString,
+		sprite_dir: GString,
+		width: i64,
+		height: i64,
+		every: i64,
+	) -> i64 {
+		if !Self::valid_render_args(width, height, every) {
+			return -1;
+		}
+
+		Renderer::with_sprites(width as usize, height as usize, &sprite_dir.to_string())
+			.and_then(|renderer| {
+				renderer.render_replay_pngs(self, dir.to_string(), every as usize)
+			})
+			.map_or(-1, |frames| frames as i64)
+	}
+
+	/// Writes every `every`th frame into `path` as an animated GIF. Players are drawn as boxes
+	/// if `sprite_dir` is empty.
+	#[func]
+	pub fn render_gif(
+		&self,
+		path: GString,
+		sprite_dir: GString,
+		width: i64,
+		height: i64,
+		every: i64,
+	) -> bool {
+		if !Self::valid_render_args(width, height, every) {
+			return false;
+		}
+
+		Renderer::with_sprites(width as usize, height as usize, &sprite_dir.to_string())
+			.and_then(|renderer| {
+				let file = File::create(path.to_string())?;
+				renderer.render_replay_gif(
+					self,
+					BufWriter::new(file),
+					every as usize,
+				)
+			})
+			.is_ok()
+	}
+
+	fn valid_render_args(width: i64, height: i64, every: i64) -> bool {
+		let valid = width > 0 && height > 0 && every > 0;
+		if !valid {
+			godot_error!(
+				"Replay: render size and frame step must be positive, got {width}x{height} \
+				 every {every}"
+			);
+		}
+
+		valid
+	}
+
+	#[func]
+	pub fn to_json(&self) -> GString {
+		// Only plain numbers and bools, serialization can not fail
+		GString::from(serde_json::to_string(self).unwrap())
+	}
+
+	#[func]
+	pub fn from_json(json: GString) -> Option<Gd<Self>> {
+		serde_json::from_str(&json.to_string())
+			.ok()
+			.map(Gd::from_object)
+	}
+}
@@ -0,0 +1,121 @@
+use std::hash::Hasher;
+
+/// SplitMix64, small, fast and good enough for gameplay randomness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rng {
+	state: u64,
+}
+
+impl Rng {
+	#[inline]
+	pub const fn new(seed: u64) -> Self {
+		Rng { state: seed }
+	}
+
+	#[inline]
+	pub const fn next_u64(&mut self) -> u64 {
+		self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+
+		let mut z = self.state;
+		z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+		z ^ (z >> 31)
+	}
+
+	/// Uniform in `[0, 1)`
+	#[inline]
+	pub fn next_f32(&mut self) -> f32 {
+		// 24 bits is all the precision an f32 has
+		(self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+	}
+
+	/// Uniform in `[min, max]`, `min` must not be greater than `max`.
+	#[inline]
+	pub const fn range(&mut self, min: i64, max: i64) -> i64 {
+		let len = max.abs_diff(min) + 1;
+		// Only the full i64 range overflows, where any value will do
+		match len {
+			0 => self.next_u64() as i64,
+			_ => min.wrapping_add((self.next_u64() % len) as i64),
+		}
+	}
+
+	#[inline]
+	pub fn chance(&mut self, probability: f32) -> bool {
+		self.next_f32() < probability
+	}
+}
+
+/// FNV-1a, unlike `DefaultHasher` it is not randomly keyed, so hashes are the same every run.
+#[derive(Debug, Clone, Copy)]
+pub struct StateHasher {
+	state: u64,
+}
+
+impl StateHasher {
+	pub const fn new() -> Self {
+		StateHasher {
+			state: 0xCBF29CE484222325,
+		}
+	}
+}
+
+impl Default for StateHasher {
+	#[inline]
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Hasher for StateHasher {
+	#[inline]
+	fn write(&mut self, bytes: &[u8]) {
+		for &byte in bytes {
+			self.state ^= byte as u64;
+			self.state = self.state.wrapping_mul(0x100000001B3);
+		}
+	}
+
+	#[inline]
+	fn finish(&self) -> u64 {
+		self.state
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::Rng;
+
+	#[test]
+	fn deterministic() {
+		let mut rng1 = Rng::new(42);
+		let mut rng2 = Rng::new(42);
+		let mut rng3 = Rng::new(43);
+
+		for _ in 0..100 {
+			let val = rng1.next_u64();
+			assert_eq!(val, rng2.next_u64());
+			assert_ne!(val, rng3.next_u64());
+		}
+	}
+
+	#[test]
+	fn next_f32() {
+		let mut rng = Rng::new(0);
+
+		for _ in 0..1000 {
+			let val = rng.next_f32();
+			assert!((0.0..1.0).contains(&val));
+		}
+	}
+
+	#[test]
+	fn range() {
+		let mut rng = Rng::new(0);
+
+		for _ in 0..1000 {
+			assert!((-3..=5).contains(&rng.range(-3, 5)));
+		}
+		assert_eq!(rng.range(7, 7), 7);
+	}
+}
@@ -0,0 +1,157 @@
+use godot::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	player::{Player, PlayerState},
+	simul::Match,
+};
+
+/// One side of a `Scenario`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScenarioPlayer {
+	/// In stage units, from player 1's wall
+	pub position: i16,
+	/// The handicap's starting guard if not given
+	#[serde(default)]
+	pub guard_points: Option<u8>,
+	/// Any frame of any move, like `{"NNormal": [5, false]}`
+	#[serde(default = "ScenarioPlayer::idle")]
+	pub state: PlayerState,
+	/// Frames attack has been held for, to start with the special charged
+	#[serde(default)]
+	pub hold: u8,
+	#[serde(default)]
+	pub wins: u8,
+}
+
+impl ScenarioPlayer {
+	const fn idle() -> PlayerState {
+		PlayerState::Idle(0)
+	}
+}
+
+/// Situation a round starts from, for training punishes, corner escapes and such directly.
+/// Scenario files are the JSON form, only the positions are required:
+///
+/// ```json
+/// {
+///     "player1": { "position": 100, "guard_points": 1 },
+///     "player2": { "position": 300, "state": { "MNormal": [20, false] } },
+///     "timer": 600
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, GodotClass)]
+#[class(no_init)]
+pub struct Scenario {
+	pub player1: ScenarioPlayer,
+	pub player2: ScenarioPlayer,
+	/// Frames left in the round, the usual round time if not given
+	#[serde(default)]
+	pub timer: Option<u16>,
+}
+
+#[godot_api]
+impl Scenario {
+	#[func]
+	pub fn to_json(&self) -> GString {
+		// Only plain numbers and enums, serialization can not fail
+		GString::from(serde_json::to_string(self).unwrap())
+	}
+
+	/// `None` if the JSON is not a scenario or the scenario is not valid.
+	#[func]
+	pub fn from_json(json: GString) -> Option<Gd<Self>> {
+		serde_json::from_str::<Self>(&json.to_string())
+			.ok()
+			.filter(Self::is_valid)
+			.map(Gd::from_object)
+	}
+
+	/// Positions are on the stage with player 1 on the left, states exist and nobody is dead,
+	/// and the timer is not over.
+	pub fn is_valid(&self) -> bool {
+		let valid_player = |player: &ScenarioPlayer| {
+			(0..=Match::STAGE_LEN).contains(&player.position)
+				&& !matches!(player.state, PlayerState::Dead(_))
+				&& Player::move_data(player.state).is_some()
+		};
+
+		valid_player(&self.player1)
+			&& valid_player(&self.player2)
+			&& self.player1.position <= self.player2.position
+			&& self.timer.is_none_or(|timer| timer > 0)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::Scenario;
+	use crate::{
+		input::{FgInput, InputConfig},
+		player::PlayerState,
+		simul::{self, Match},
+	};
+
+	const NONE: FgInput = FgInput::new(0, 0, false, false);
+
+	fn parse(json: &str) -> Option<Scenario> {
+		serde_json::from_str::<Scenario>(json)
+			.ok()
+			.filter(Scenario::is_valid)
+	}
+
+	#[test]
+	fn files() {
+		for json in [
+			include_str!("../../godot/scenarios/corner_escape.json"),
+			include_str!("../../godot/scenarios/guard_break.json"),
+			include_str!("../../godot/scenarios/punish_mnormal.json"),
+		] {
+			assert!(parse(json).is_some());
+		}
+
+		// The normal is not that long
+		assert!(parse(
+			r#"{"player1": {"position": 0}, "player2": {"position": 10, "state": {"NNormal": [200, false]}}}"#
+		)
+		.is_none());
+		assert!(
+			parse(r#"{"player1": {"position": -5}, "player2": {"position": 10}}"#)
+				.is_none()
+		);
+		// Sides swapped
+		assert!(
+			parse(r#"{"player1": {"position": 300}, "player2": {"position": 100}}"#)
+				.is_none()
+		);
+		assert!(parse(
+			r#"{"player1": {"position": 100}, "player2": {"position": 300, "state": {"Dead": false}}}"#
+		)
+		.is_none());
+	}
+
+	#[test]
+	fn load() {
+		let scenario = parse(r#"{
+				"player1": {"position": 600, "guard_points": 0, "wins": 2},
+				"player2": {"position": 900, "state": {"Hit": 3}},
+				"timer": 10
+			}"#)
+		.unwrap();
+
+		let mut sim = Match::new(InputConfig::human(), InputConfig::human());
+		assert!(sim.load_scenario(&scenario));
+		assert!(sim.is_active());
+		assert_eq!(sim.p1_pos(), 600);
+		assert_eq!(sim.player_guard(true), 0);
+		assert_eq!(sim.p1_wins(), 2);
+		assert_eq!(sim.player(false).state(), PlayerState::Hit(3));
+
+		let mut res = simul::Result::Continue;
+		for _ in 0..10 {
+			res = sim.step(NONE, NONE);
+		}
+		// Timeouts go to whoever has more guard points left
+		assert_eq!(res, simul::Result::Player2);
+	}
+}
@@ -1,12 +1,25 @@
 use crate::{
-	framedata::CBox,
-	input::FgInput,
-	player::{Player, PlayerState},
+	advantage::{FrameAdvantage, Interaction},
+	delay::InputDelay,
+	domain::DomainRandomization,
+	framedata::{CBox, WorldBox},
+	history::HistoryEntry,
+	input::{FgInput, InputConfig},
+	pixelobs::{PixelObs, PixelObsMode},
+	player::{Handicap, Player, PlayerState},
+	recorder::InputRecorder,
+	rng::{Rng, StateHasher},
+	scenario::Scenario,
 	timer::Timer,
+	training::{Dummy, DummyBehaviour, TrainingMode},
 };
+use std::hash::{Hash, Hasher};
+
 use godot::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, GodotClass)]
+/// Cloning a match snapshots it, the clone plays on exactly like the original.
+#[derive(Debug, Clone, GodotClass)]
 #[class(no_init)]
 pub struct Match {
 	timer: Timer,
@@ -14,6 +27,15 @@ pub struct Match {
 	player1: Player,
 	player2: Player,
 	state: GameState,
+	training: Option<TrainingMode>,
+	delays: [Option<InputDelay>; 2],
+	/// Reaction delays rolled for the round, used instead of `delays`
+	random_delays: [Option<InputDelay>; 2],
+	/// Walking speeds rolled for the round, used instead of the handicaps'
+	random_walk_speeds: Option<[u8; 2]>,
+	domain: Option<DomainRandomization>,
+	/// All randomness of the match comes from here, so seeded matches are reproducible
+	rng: Rng,
 }
 
 #[godot_api]
@@ -25,20 +47,52 @@ impl Match {
 	const ROUND_START_LEN: u8 = 90;
 	const HITSTOP_LEN: u8 = 15;
 	const ROUND_END_LEN: u8 = 60;
-
+	/// Values before the one-hot states in `player_obs`
+	const PLAYER_OBS_VALUES: usize = 13;
+	/// Length of `player_obs`
+	pub const PLAYER_OBS_LEN: usize = Self::PLAYER_OBS_VALUES
+		+ (PlayerState::STATE_COUNT * 2 + GameState::STATE_COUNT) as usize;
+	/// Length of `punish_obs`
+	pub const PUNISH_OBS_LEN: usize = 4;
+	/// Sounds `player_sound` returns the index of, named like the files in `godot/audio`
+	pub const SOUNDS: [&'static str; 10] = [
+		"fdash",
+		"bdash",
+		"block",
+		"guard_break",
+		"hit",
+		"nnormal",
+		"mnormal",
+		"nspecial",
+		"mspecial",
+		"ender_hit",
+	];
+
+	/// Bots and humans get the `InputConfig::bot` and `InputConfig::human` presets.
 	#[func]
 	pub fn gd_new(p1_bot: bool, p2_bot: bool) -> Gd<Self> {
-		Gd::from_object(Self::new(p1_bot, p2_bot))
+		Gd::from_object(Self::new(InputConfig::preset(p1_bot), InputConfig::preset(p2_bot)))
+	}
+
+	#[func]
+	pub fn gd_with_input_configs(config1: Gd<InputConfig>, config2: Gd<InputConfig>) -> Gd<Self> {
+		Gd::from_object(Self::new(*config1.bind(), *config2.bind()))
 	}
 
-	pub const fn new(p1_bot: bool, p2_bot: bool) -> Self {
+	pub const fn new(config1: InputConfig, config2: InputConfig) -> Self {
 		Match {
 			timer: Timer::Limited(Self::ROUND_TIME),
 			//timer: Timer::Unlimited,
 			rounds: 0,
-			player1: Player::new(Self::starting_position(true), p1_bot),
-			player2: Player::new(Self::starting_position(false), p2_bot),
+			player1: Player::new(Self::starting_position(true), config1),
+			player2: Player::new(Self::starting_position(false), config2),
 			state: GameState::RoundStart(Self::ROUND_START_LEN),
+			training: None,
+			delays: [None, None],
+			random_delays: [None, None],
+			random_walk_speeds: None,
+			domain: None,
+			rng: Rng::new(0),
 		}
 	}
 
@@ -50,19 +104,53 @@ impl Match {
 		*self = Match {
 			player1: self.player1.clone(),
 			player2: self.player2.clone(),
-			// p1_bot and p2_bot does not matter
-			..Match::new(false, false)
+			training: self.training.take(),
+			delays: std::mem::take(&mut self.delays),
+			domain: self.domain.take(),
+			rng: self.rng,
+			// Input configs are kept by the players
+			..Match::new(InputConfig::human(), InputConfig::human())
 		};
+		self.timer = self.round_timer();
+		self.randomize_round();
 	}
 
 	#[func]
 	pub fn frame_update(&mut self, input1: Gd<FgInput>, input2: Gd<FgInput>) -> Result {
+		self.step(*input1.bind(), *input2.bind())
+	}
+
+	pub fn step(&mut self, input1: FgInput, input2: FgInput) -> Result {
+		let rng = &mut self.rng;
+		let [delay1, delay2] = &mut self.delays;
+		let [random1, random2] = &mut self.random_delays;
+		let input1 = random1
+			.as_mut()
+			.or(delay1.as_mut())
+			.map_or(input1, |delay| delay.delay(rng, input1));
+		let input2 = random2
+			.as_mut()
+			.or(delay2.as_mut())
+			.map_or(input2, |delay| delay.delay(rng, input2));
+
+		let (input1, input2) = match &self.domain {
+			Some(domain) => (
+				Self::drop_input(domain, rng, &self.player1, input1),
+				Self::drop_input(domain, rng, &self.player2, input2),
+			),
+			None => (input1, input2),
+		};
+
+		let (input1, input2) = match &mut self.training {
+			Some(training) => {
+				training.inputs(rng, &self.player1, &self.player2, input1, input2)
+			}
+			None => (input1, input2),
+		};
+
 		self.player1.counter_hit = false;
 		self.player2.counter_hit = false;
 
-		let input1 = *input1.bind();
-		let input2 = *input2.bind();
-
 		self.player1.set_input(input1);
 		self.player2.set_input(input2);
 
@@ -87,6 +175,18 @@ impl Match {
 
 		self.combat_update();
 
+		if let Some(training) = &mut self.training {
+			training.advantage.update(&self.player1, &self.player2);
+			if self.player1.is_dead() || self.player2.is_dead() || self.timer.is_over() {
+				training.advantage.end_round();
+			}
+
+			if training.infinite_guard {
+				self.player1.refill_guard();
+				self.player2.refill_guard();
+			}
+		}
+
 		if self.player1.is_dead() || self.player2.is_dead() {
 			self.state = GameState::RoundEnd(Self::ROUND_END_LEN);
 			return Result::Continue;
@@ -111,7 +211,10 @@ impl Match {
 		let p2_move = self.player2.update_move();
 
 		// Update movement
-		self.position_update(p1_move.data.speed, p2_move.data.speed);
+		self.position_update(
+			self.player1.speed(p1_move.data.speed, self.walk_speed(true)),
+			self.player2.speed(p2_move.data.speed, self.walk_speed(false)),
+		);
 
 		// Update char collision
 		self.collision_update(p1_move.data.collision, p2_move.data.collision);
@@ -176,6 +279,431 @@ impl Match {
 		}
 	}
 
+	#[func]
+	pub fn gd_load_scenario(&mut self, scenario: Gd<Scenario>) -> bool {
+		self.load_scenario(&scenario.bind())
+	}
+
+	/// Restarts the round from the scenario, skipping the round start. Later rounds start as
+	/// usual. Nothing changes if the scenario is not valid.
+	pub fn load_scenario(&mut self, scenario: &Scenario) -> bool {
+		if !scenario.is_valid() {
+			return false;
+		}
+
+		for (p1, setup) in [(true, scenario.player1), (false, scenario.player2)] {
+			let player = self.player_mut(p1);
+
+			player.reset(setup.position);
+			player.wins = setup.wins;
+			if let Some(guard_points) = setup.guard_points {
+				player.guard_points = guard_points;
+			}
+			player.set_state(setup.state);
+			player.set_hold_time(setup.hold);
+		}
+
+		self.timer = scenario.timer.map_or(self.round_timer(), Timer::Limited);
+		self.state = GameState::Active;
+
+		true
+	}
+
+	/// Restarts the random number generator, the same seed and inputs replay the same match.
+	#[func]
+	pub fn set_seed(&mut self, seed: i64) {
+		self.rng = Rng::new(seed as u64);
+	}
+
+	#[func]
+	pub fn gd_mirrored(&self) -> Gd<Self> {
+		Gd::from_object(self.mirrored())
+	}
+
+	/// The same match with the players swapped, player 1 of the original is player 2 of the
+	/// mirrored match and stands where it would on the other side of the stage. `FgInput`s are
+	/// relative to facing, so stepping the mirrored match with the inputs swapped plays out the
+	/// same, with mirrored results. Randomness is drawn in player order, so with input jitter,
+	/// input drops or random dummy blocks the two matches may differ.
+	pub fn mirrored(&self) -> Self {
+		let [delay1, delay2] = self.delays.clone();
+		let [random1, random2] = self.random_delays.clone();
+
+		Match {
+			timer: self.timer,
+			rounds: self.rounds,
+			player1: self.player2.mirrored(),
+			player2: self.player1.mirrored(),
+			state: self.state,
+			training: self.training.as_ref().map(TrainingMode::mirrored),
+			delays: [delay2, delay1],
+			random_delays: [random2, random1],
+			random_walk_speeds: self.random_walk_speeds.map(|[p1, p2]| [p2, p1]),
+			domain: self.domain.clone(),
+			rng: self.rng,
+		}
+	}
+
+	/// The match as seen by `p1`'s side, always as player 1.
+	#[func]
+	pub fn perspective(&self, p1: bool) -> Gd<Self> {
+		match p1 {
+			true => self.snapshot(),
+			false => self.gd_mirrored(),
+		}
+	}
+
+	/// Result of the match as seen by `p1`'s side, see `perspective`.
+	#[func]
+	pub fn perspective_result(p1: bool, result: Result) -> Result {
+		match p1 {
+			true => result,
+			false => result.mirrored(),
+		}
+	}
+
+	#[func]
+	pub fn snapshot(&self) -> Gd<Self> {
+		Gd::from_object(self.clone())
+	}
+
+	/// Continues from the snapshot, as if nothing happened since it was taken.
+	#[func]
+	pub fn restore(&mut self, snapshot: Gd<Self>) {
+		*self = snapshot.bind().clone();
+	}
+
+	/// Hash of everything that decides how the match plays on: the timer, the players, the
+	/// training mode, the delayed inputs, the domain randomisation and the random number
+	/// generator. Stable between runs, for desync checks.
+	#[func]
+	pub fn state_hash(&self) -> i64 {
+		let mut hasher = StateHasher::new();
+		(
+			self.timer,
+			self.rounds,
+			&self.player1,
+			&self.player2,
+			self.state,
+			&self.training,
+			&self.delays,
+			&self.random_delays,
+			self.random_walk_speeds,
+			&self.domain,
+			self.rng,
+		)
+			.hash(&mut hasher);
+
+		hasher.finish() as i64
+	}
+
+	/// Inputs of the player reach the simulation `frames` plus up to `jitter` frames late.
+	/// `Player` input history shows the inputs after the delay.
+	#[func]
+	pub fn set_input_delay(&mut self, p1: bool, frames: u8, jitter: u8) {
+		self.delays[!p1 as usize] = Some(InputDelay::new(frames, jitter));
+	}
+
+	#[func]
+	pub fn disable_input_delay(&mut self, p1: bool) {
+		self.delays[!p1 as usize] = None;
+	}
+
+	/// Frames and jitter of the player's input delay.
+	#[inline]
+	pub fn input_delay(&self, p1: bool) -> Option<(u8, u8)> {
+		self.delays[!p1 as usize]
+			.as_ref()
+			.map(|delay| (delay.frames(), delay.jitter()))
+	}
+
+	/// Randomises the spacing and timer of every new round, and optionally walk speeds, reaction
+	/// delays and dropped inputs, see the setters below. Starts with the usual opening, and takes
+	/// effect from the next round. The setters are ignored while it is disabled.
+	#[func]
+	pub fn enable_domain_randomization(&mut self) {
+		self.domain = Some(DomainRandomization::new(
+			Self::STAGE_LEN - 2 * Self::PLAYER_START,
+			Self::ROUND_TIME,
+		));
+	}
+
+	/// Rolled walk speeds and reaction delays are dropped right away.
+	#[func]
+	pub fn disable_domain_randomization(&mut self) {
+		self.domain = None;
+		self.random_delays = [None, None];
+		self.random_walk_speeds = None;
+	}
+
+	#[inline]
+	pub fn domain_randomization(&self) -> Option<&DomainRandomization> {
+		self.domain.as_ref()
+	}
+
+	#[inline]
+	pub fn set_domain_randomization(&mut self, domain: Option<DomainRandomization>) {
+		self.domain = domain;
+	}
+
+	/// Distance between the players at round start, from 0 to `STAGE_LEN`.
+	#[func]
+	pub fn set_random_spacing(&mut self, min: i16, max: i16) {
+		if let Some(domain) = &mut self.domain {
+			if !domain.set_spacing(min, max) {
+				godot_error!("Match: random spacing {min}..={max} is not on the stage");
+			}
+		}
+	}
+
+	/// Round length in frames, unlimited timers stay unlimited.
+	#[func]
+	pub fn set_random_timer(&mut self, min: u16, max: u16) {
+		if let Some(domain) = &mut self.domain {
+			domain.timer = (min, max);
+		}
+	}
+
+	/// Walking speeds in percent, rolled for each player. Overrides the handicap's for the round,
+	/// without changing it.
+	#[func]
+	pub fn set_random_walk_speed(&mut self, min: u8, max: u8) {
+		if let Some(domain) = &mut self.domain {
+			domain.walk_speed = Some((min, max));
+		}
+	}
+
+	/// Input delays in frames, rolled for each player. Overrides `set_input_delay` for the round,
+	/// without changing it.
+	#[func]
+	pub fn set_random_reaction_delay(&mut self, min: u8, max: u8) {
+		if let Some(domain) = &mut self.domain {
+			domain.reaction_delay = Some((min, max));
+		}
+	}
+
+	#[func]
+	pub fn set_input_drop_chance(&mut self, chance: f32) {
+		if let Some(domain) = &mut self.domain {
+			domain.drop_chance = chance;
+		}
+	}
+
+	/// Training options are ignored unless training mode is on.
+	#[func]
+	pub fn set_training(&mut self, dummy_p1: bool, behaviour: DummyBehaviour) {
+		self.training = Some(TrainingMode::new(
+			dummy_p1,
+			Dummy::new(behaviour),
+			Self::STAGE_LEN - 2 * Self::PLAYER_START,
+			Self::STAGE_LEN / 2,
+		));
+	}
+
+	#[func]
+	pub fn disable_training(&mut self) {
+		self.training = None;
+		self.timer = self.round_timer();
+	}
+
+	#[func]
+	pub fn set_dummy_behaviour(&mut self, behaviour: DummyBehaviour) {
+		if let Some(training) = &mut self.training {
+			training.dummy.set_behaviour(&mut self.rng, behaviour);
+		}
+	}
+
+	/// Moves the dummy to the other player, keeping its recordings.
+	#[func]
+	pub fn set_dummy_side(&mut self, dummy_p1: bool) {
+		if let Some(training) = &mut self.training {
+			training.dummy_p1 = dummy_p1;
+		}
+	}
+
+	/// Slot that the next `DummyBehaviour::Record` records into, ignored unless it is from 0 to
+	/// `InputRecorder::SLOTS - 1`.
+	#[func]
+	pub fn set_record_slot(&mut self, slot: i64) {
+		if let Some(training) = &mut self.training {
+			if !training.dummy.set_record_slot(slot) {
+				godot_error!(
+					"Match: record slot {slot} is not from 0 to {}",
+					InputRecorder::SLOTS - 1
+				);
+			}
+		}
+	}
+
+	#[func]
+	pub fn clear_record_slot(&mut self, slot: i64) {
+		if let Some(training) = &mut self.training {
+			training.dummy.recorder.clear(slot as usize);
+		}
+	}
+
+	#[func]
+	pub fn set_playback_slot_enabled(&mut self, slot: i64, enabled: bool) {
+		if let Some(training) = &mut self.training {
+			training.dummy.recorder.set_enabled(slot as usize, enabled);
+		}
+	}
+
+	#[func]
+	pub fn set_playback_loop(&mut self, looping: bool) {
+		if let Some(training) = &mut self.training {
+			training.dummy.recorder.looping = looping;
+		}
+	}
+
+	#[func]
+	pub fn set_max_record_frames(&mut self, frames: i64) {
+		if let Some(training) = &mut self.training {
+			training.dummy
+				.recorder
+				.set_max_frames(frames.max(0) as usize);
+		}
+	}
+
+	#[func]
+	pub fn record_slot_len(&self, slot: i64) -> i64 {
+		match &self.training {
+			Some(training) => training.dummy.recorder.slot_len(slot as usize) as i64,
+			None => 0,
+		}
+	}
+
+	#[func]
+	pub fn set_block_chance(&mut self, chance: f32) {
+		if let Some(training) = &mut self.training {
+			training.dummy.block_chance = chance;
+		}
+	}
+
+	#[func]
+	pub fn set_infinite_guard(&mut self, infinite: bool) {
+		if let Some(training) = &mut self.training {
+			training.infinite_guard = infinite;
+		}
+	}
+
+	#[func]
+	pub fn set_unlimited_time(&mut self, unlimited: bool) {
+		if let Some(training) = &mut self.training {
+			training.unlimited_time = unlimited;
+			self.timer = self.round_timer();
+		}
+	}
+
+	/// Ignored unless both players fit on the stage, `spacing` apart around `center`.
+	#[func]
+	pub fn set_reset_spacing(&mut self, spacing: i16, center: i16) {
+		if let Some(training) = &mut self.training {
+			if !training.set_spacing(spacing, center) {
+				godot_error!("Match: reset spacing {spacing} around {center} is not on the stage");
+			}
+		}
+	}
+
+	#[inline]
+	pub fn in_training(&self) -> bool {
+		self.training.is_some()
+	}
+
+	#[inline]
+	pub fn frame_advantage(&self) -> Option<&FrameAdvantage> {
+		self.training.as_ref().map(|training| &training.advantage)
+	}
+
+	/// Number of finished hit and block interactions, changes whenever `last_interaction` does.
+	#[func]
+	pub fn interaction_count(&self) -> i64 {
+		self.frame_advantage().map_or(0, |x| x.count() as i64)
+	}
+
+	/// Empty until the first interaction of the training mode finishes.
+	#[func]
+	pub fn last_interaction(&self) -> Dictionary {
+		self.frame_advantage()
+			.and_then(FrameAdvantage::last)
+			.map_or(Dictionary::new(), Interaction::to_dictionary)
+	}
+
+	/// Puts the players back to neutral at the spacing of the training mode, mid-round.
+	#[func]
+	pub fn reset_positions(&mut self) {
+		let Some(training) = &self.training else {
+			return;
+		};
+
+		let p1_pos = training.center - training.spacing / 2;
+		let p2_pos = p1_pos + training.spacing;
+
+		self.player1.reset(p1_pos.clamp(0, Self::STAGE_LEN));
+		self.player2.reset(p2_pos.clamp(0, Self::STAGE_LEN));
+		self.state = GameState::Active;
+	}
+
+	/// Dropped inputs keep the held buttons of the last frame, like a frame the game never saw.
+	#[inline]
+	fn drop_input(
+		domain: &DomainRandomization,
+		rng: &mut Rng,
+		player: &Player,
+		input: FgInput,
+	) -> FgInput {
+		if !domain.drops_input(rng) {
+			return input;
+		}
+
+		player.history.iter().next().map_or(FgInput::new(0, 0, false, false), |last| FgInput {
+			movement_press: 0,
+			attack_press: false,
+			..last.input
+		})
+	}
+
+	fn randomize_round(&mut self) {
+		let Some(domain) = &self.domain else {
+			return;
+		};
+
+		let params = domain.roll(&mut self.rng);
+
+		let p1_pos = Self::STAGE_LEN / 2 - params.spacing / 2;
+		self.player1.reset(p1_pos.clamp(0, Self::STAGE_LEN));
+		self.player2.reset((p1_pos + params.spacing).clamp(0, Self::STAGE_LEN));
+
+		if let Timer::Limited(_) = self.timer {
+			self.timer = Timer::Limited(params.timer.max(1));
+		}
+
+		self.random_walk_speeds = params.walk_speeds;
+
+		if let Some(reaction_delays) = params.reaction_delays {
+			for (delay, frames) in self.random_delays.iter_mut().zip(reaction_delays) {
+				*delay = Some(InputDelay::new(frames, 0));
+			}
+		}
+	}
+
+	/// Walking speed of the player in percent, the rolled one if there is one.
+	#[inline]
+	pub fn walk_speed(&self, p1: bool) -> u8 {
+		match self.random_walk_speeds {
+			Some(walk_speeds) => walk_speeds[!p1 as usize],
+			None => self.player(p1).handicap().walk_speed,
+		}
+	}
+
+	#[inline]
+	fn round_timer(&self) -> Timer {
+		match &self.training {
+			Some(training) if training.unlimited_time => Timer::Unlimited,
+			_ => Timer::Limited(Self::ROUND_TIME),
+		}
+	}
+
 	#[inline]
 	const fn starting_position(player1: bool) -> i16 {
 		if player1 {
@@ -217,6 +745,17 @@ impl Match {
 		}
 	}
 
+	#[func]
+	pub fn end_reason(&self) -> EndReason {
+		if self.player1.is_dead() || self.player2.is_dead() {
+			EndReason::Ko
+		} else if self.player1.guard_points != self.player2.guard_points {
+			EndReason::Guard
+		} else {
+			EndReason::Position
+		}
+	}
+
 	#[inline]
 	const fn update_wins(&mut self, result: Result) {
 		match result {
@@ -274,6 +813,68 @@ impl Match {
 		(self.player1.position - self.player2.position).abs()
 	}
 
+	#[inline]
+	pub const fn player(&self, p1: bool) -> &Player {
+		match p1 {
+			true => &self.player1,
+			false => &self.player2,
+		}
+	}
+
+	#[inline]
+	pub const fn player_mut(&mut self, p1: bool) -> &mut Player {
+		match p1 {
+			true => &mut self.player1,
+			false => &mut self.player2,
+		}
+	}
+
+	/// Collision box of the player in the stage, see `WorldBox`.
+	#[inline]
+	pub fn collision_box(&self, p1: bool) -> WorldBox {
+		let player = self.player(p1);
+		player.get_move().data.collision.world(player.position, !p1)
+	}
+
+	#[inline]
+	pub fn hurtboxes(&self, p1: bool) -> impl Iterator<Item = WorldBox> {
+		let player = self.player(p1);
+		let position = player.position;
+
+		player.get_move()
+			.data
+			.hurtbox
+			.into_iter()
+			.flatten()
+			.map(move |hurt| hurt.world(position, !p1))
+	}
+
+	#[inline]
+	pub fn hitbox(&self, p1: bool) -> Option<WorldBox> {
+		let player = self.player(p1);
+		player.get_move()
+			.data
+			.hitbox
+			.map(|hit| hit.world(player.position, !p1))
+	}
+
+	/// Boxes are in stage units, y grows upwards from the floor.
+	#[func]
+	pub fn player_collision_box(&self, p1: bool) -> Rect2 {
+		self.collision_box(p1).into()
+	}
+
+	#[func]
+	pub fn player_hurtboxes(&self, p1: bool) -> Array<Rect2> {
+		self.hurtboxes(p1).map(Rect2::from).collect()
+	}
+
+	/// Empty or a single box.
+	#[func]
+	pub fn player_hitboxes(&self, p1: bool) -> Array<Rect2> {
+		self.hitbox(p1).into_iter().map(Rect2::from).collect()
+	}
+
 	#[func]
 	pub fn p1_pos(&self) -> i16 {
 		self.player1.position
@@ -446,26 +1047,44 @@ impl Match {
 
 	#[func]
 	pub fn audio(&self) -> Vec<GString> {
-		let mut res = Vec::new();
+		self.audio_names().map(GString::from).collect()
+	}
 
-		// Prevent audio spamming on hitstop, round end and round finish
-		match self.state {
-			GameState::Hitstop(..Self::HITSTOP_LEN)
-			| GameState::RoundEnd(..Self::ROUND_END_LEN)
-			| GameState::RoundFinish => {
-				return res;
-			}
-			_ => (),
-		}
+	/// Sounds of this frame, player 1's first. Unlike `audio`, nothing is allocated.
+	pub fn audio_names(&self) -> impl Iterator<Item = &'static str> {
+		let muted = self.audio_muted();
 
-		if let Some(audio) = self.player1.get_audio() {
-			res.push(GString::from(audio));
-		};
-		if let Some(audio) = self.player2.get_audio() {
-			res.push(GString::from(audio));
-		};
+		[self.player1.get_audio(), self.player2.get_audio()]
+			.into_iter()
+			.flatten()
+			.filter(move |_| !muted)
+	}
 
-		res
+	/// `SOUNDS`, for looking up `player_sound` once instead of calling `audio` every frame.
+	#[func]
+	pub fn sound_names() -> Vec<GString> {
+		Self::SOUNDS.into_iter().map(GString::from).collect()
+	}
+
+	/// Index of the player's sound of this frame in `sound_names`, -1 if it has none.
+	#[func]
+	pub fn player_sound(&self, p1: bool) -> i64 {
+		let audio = self.player(p1).get_audio().filter(|_| !self.audio_muted());
+
+		audio
+			.and_then(|name| Self::SOUNDS.iter().position(|&sound| sound == name))
+			.map_or(-1, |id| id as i64)
+	}
+
+	#[inline]
+	fn audio_muted(&self) -> bool {
+		// Prevent audio spamming on hitstop, round end and round finish
+		matches!(
+			self.state,
+			GameState::Hitstop(..Self::HITSTOP_LEN)
+				| GameState::RoundEnd(..Self::ROUND_END_LEN)
+				| GameState::RoundFinish
+		)
 	}
 
 	#[func]
@@ -473,6 +1092,11 @@ impl Match {
 		self.player1.wins < 3 && self.player2.wins < 3
 	}
 
+	#[inline]
+	pub const fn is_active(&self) -> bool {
+		matches!(self.state, GameState::Active)
+	}
+
 	#[func]
 	pub fn state(&self) -> i64 {
 		self.state.into()
@@ -488,14 +1112,86 @@ impl Match {
 		Self::STAGE_LEN
 	}
 
+	/// Reward of the agent playing as `p1` for the frame that returned `res`. Shared by
+	/// `battle_scene.gd` and the behaviour cloning export, so both score frames the same.
+	#[func]
+	pub fn ai_reward(&self, res: Result, p1: bool) -> f32 {
+		let mut reward = 0.0;
+
+		if res == Result::Continue {
+			// Step penalty
+			reward -= 0.15;
+			// Corner
+			if self.player_relative_pos(p1) < 200 {
+				reward -= 0.50;
+			}
+			// Distance penalty, 0 up to 710
+			reward -= (self.player_distance().max(710) as f32).powf(0.4) - 13.82;
+		}
+
+		let events = [
+			(self.player_hold(p1) >= 30, 0.30),
+			(self.player_hit(!p1), 20.0),
+			(self.player_block(!p1), 10.0),
+			(self.player_block(p1), 10.0),
+			(self.player_block_ender(!p1), -30.0),
+			(self.player_guard_break(!p1), 30.0),
+			(self.player_dead(!p1), 100.0),
+			(self.player_hit(p1), -20.0),
+			(self.player_dead(p1), -25.0),
+		];
+
+		reward
+			+ events
+				.iter()
+				.filter(|(happened, _)| *happened)
+				.map(|(_, reward)| reward)
+				.sum::<f32>()
+	}
+
 	#[func]
 	pub fn player_obs(&self, p1: bool) -> Vec<f32> {
+		let mut res = vec![0.0; Self::PLAYER_OBS_LEN];
+		self.player_obs_into(p1, &mut res);
+
+		res
+	}
+
+	/// `player_obs` in a single allocation, the engine does not box every element like it does
+	/// for arrays.
+	#[func]
+	pub fn player_obs_packed(&self, p1: bool) -> PackedFloat32Array {
+		let mut res = PackedFloat32Array::new();
+		res.resize(Self::PLAYER_OBS_LEN);
+		self.player_obs_into(p1, res.as_mut_slice());
+
+		res
+	}
+
+	/// Writes `player_obs` into the start of `out` and returns it, `out` is only resized if it is
+	/// shorter than `PLAYER_OBS_LEN`. Packed arrays are copy-on-write, so the returned array has
+	/// the observation, not the caller's: `obs = sim.player_obs_fill(true, obs)`.
+	#[func]
+	pub fn player_obs_fill(&self, p1: bool, mut out: PackedFloat32Array) -> PackedFloat32Array {
+		if out.len() < Self::PLAYER_OBS_LEN {
+			out.resize(Self::PLAYER_OBS_LEN);
+		}
+		self.player_obs_into(p1, out.as_mut_slice());
+
+		out
+	}
+
+	/// Writes `player_obs` into the start of `out`, without allocating.
+	///
+	/// Panics if `out` is shorter than `PLAYER_OBS_LEN`.
+	pub fn player_obs_into(&self, p1: bool, out: &mut [f32]) {
 		let (player, opponent) = match p1 {
 			true => (&self.player1, &self.player2),
 			false => (&self.player2, &self.player1),
 		};
 
-		let mut res = vec![
+		let (values, one_hot) = out[..Self::PLAYER_OBS_LEN].split_at_mut(Self::PLAYER_OBS_VALUES);
+		values.copy_from_slice(&[
 			self.player_relative_pos(p1) as f32 / Self::STAGE_LEN as f32,
 			self.player_relative_pos(!p1) as f32 / Self::STAGE_LEN as f32,
 			self.player_distance() as f32 / Self::STAGE_LEN as f32,
@@ -509,38 +1205,118 @@ impl Match {
 			opponent.can_block() as i32 as f32,
 			player.hold_time() as f32 / 60f32,
 			self.state_len() as f32 / 60f32,
-		];
+		]);
+
+		// player, opponent and game states as one-hot encodings
+		let (player_state, rest) = one_hot.split_at_mut(PlayerState::STATE_COUNT as usize);
+		let (opponent_state, game_state) = rest.split_at_mut(PlayerState::STATE_COUNT as usize);
+		for (one_hot, state) in [
+			(player_state, player.state_int()),
+			(opponent_state, opponent.state_int()),
+			(game_state, self.state()),
+		] {
+			one_hot.fill(0.0);
+			if let Some(x) = usize::try_from(state).ok().and_then(|i| one_hot.get_mut(i)) {
+				*x = 1.0;
+			}
+		}
+	}
 
-		// To lessen the reallocations
-		res.reserve(((PlayerState::STATE_COUNT * 2) + GameState::STATE_COUNT) as usize);
+	#[func]
+	pub fn input_config(&self, p1: bool) -> Gd<InputConfig> {
+		Gd::from_object(self.player(p1).input_config())
+	}
 
-		// player state as one-hot encoding
-		res.extend((0..PlayerState::STATE_COUNT)
-			.map(|x| (x == player.state_int()) as i32 as f32));
+	#[func]
+	pub fn handicap(&self, p1: bool) -> Gd<Handicap> {
+		Gd::from_object(self.player(p1).handicap())
+	}
 
-		// opponent state as one-hot encoding
-		res.extend((0..PlayerState::STATE_COUNT)
-			.map(|x| (x == opponent.state_int()) as i32 as f32));
+	/// Meant to be set before the match starts, the guard points are reset.
+	#[func]
+	pub fn set_handicap(&mut self, p1: bool, handicap: Gd<Handicap>) {
+		self.player_mut(p1).set_handicap(*handicap.bind());
+	}
 
-		// game state as one-hot encoding
-		res.extend((0..GameState::STATE_COUNT).map(|x| (x == self.state()) as i32 as f32));
+	/// Takes effect from the next frame, input buffers already made are kept.
+	#[func]
+	pub fn set_input_config(&mut self, p1: bool, config: Gd<InputConfig>) {
+		self.player_mut(p1).set_input_config(*config.bind());
+	}
 
-		res
+	/// Last inputs of the player, newest first. Each entry has the `FgInput` fields and `frames`,
+	/// how long the input was held.
+	#[func]
+	pub fn input_history(&self, p1: bool) -> Array<Dictionary> {
+		self.player(p1)
+			.history
+			.iter()
+			.map(HistoryEntry::to_dictionary)
+			.collect()
+	}
+
+	/// Low resolution render of the boxes, see `PixelObs::observe`. Empty if the size is not
+	/// positive, sizes above `PixelObs::MAX_SIZE` are clamped.
+	#[func]
+	pub fn pixel_obs(&self, p1: bool, width: i64, height: i64, mode: PixelObsMode) -> Vec<f32> {
+		if width <= 0 || height <= 0 {
+			godot_error!("Match: pixel observation size must be positive, got {width}x{height}");
+			return Vec::new();
+		}
+
+		let max = PixelObs::MAX_SIZE as i64;
+		PixelObs::new(width.min(max) as usize, height.min(max) as usize, mode).observe(self, p1)
+	}
+
+	#[func]
+	pub fn pixel_obs_channels(mode: PixelObsMode) -> i64 {
+		mode.channels() as i64
 	}
 
 	#[func]
 	pub fn punish_obs(&self, p1: bool) -> Vec<f32> {
+		let mut res = vec![0.0; Self::PUNISH_OBS_LEN];
+		self.punish_obs_into(p1, &mut res);
+
+		res
+	}
+
+	/// `punish_obs` in a single allocation, see `player_obs_packed`.
+	#[func]
+	pub fn punish_obs_packed(&self, p1: bool) -> PackedFloat32Array {
+		let mut res = PackedFloat32Array::new();
+		res.resize(Self::PUNISH_OBS_LEN);
+		self.punish_obs_into(p1, res.as_mut_slice());
+
+		res
+	}
+
+	/// `punish_obs` into a caller's array, see `player_obs_fill`.
+	#[func]
+	pub fn punish_obs_fill(&self, p1: bool, mut out: PackedFloat32Array) -> PackedFloat32Array {
+		if out.len() < Self::PUNISH_OBS_LEN {
+			out.resize(Self::PUNISH_OBS_LEN);
+		}
+		self.punish_obs_into(p1, out.as_mut_slice());
+
+		out
+	}
+
+	/// Writes `punish_obs` into the start of `out`, without allocating.
+	///
+	/// Panics if `out` is shorter than `PUNISH_OBS_LEN`.
+	pub fn punish_obs_into(&self, p1: bool, out: &mut [f32]) {
 		let (player, opponent) = match p1 {
 			true => (&self.player1, &self.player2),
 			false => (&self.player2, &self.player1),
 		};
 
-		vec![
+		out[..Self::PUNISH_OBS_LEN].copy_from_slice(&[
 			Self::can_punish_nnormal(player, opponent, p1) as i32 as f32,
 			Self::can_punish_mnormal(player, opponent, p1) as i32 as f32,
 			Self::can_punish_nspecial(player, opponent, p1) as i32 as f32,
 			Self::can_punish_mspecial(player, opponent, p1) as i32 as f32,
-		]
+		]);
 	}
 
 	#[inline]
@@ -624,7 +1400,7 @@ impl Match {
 	}
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum GameState {
 	RoundStart(u8),
 	Active,
@@ -683,10 +1459,10 @@ impl GameState {
 	}
 }
 
-impl Into<i64> for GameState {
+impl From<GameState> for i64 {
 	#[inline]
-	fn into(self) -> i64 {
-		match self {
+	fn from(state: GameState) -> i64 {
+		match state {
 			GameState::RoundStart(_) => 0,
 			GameState::Active => 1,
 			GameState::Hitstop(_) => 2,
@@ -696,7 +1472,7 @@ impl Into<i64> for GameState {
 	}
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, GodotConvert, Var, Export)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GodotConvert, Var, Export, Serialize, Deserialize)]
 #[godot(via = i64)]
 pub enum Result {
 	Continue,
@@ -706,3 +1482,213 @@ pub enum Result {
 	Draw,
 	Timeout,
 }
+
+impl Result {
+	/// Same result with the players swapped.
+	#[inline]
+	pub const fn mirrored(self) -> Self {
+		match self {
+			Result::Player1 => Result::Player2,
+			Result::Player2 => Result::Player1,
+			res => res,
+		}
+	}
+}
+
+/// Why a round ended, only meaningful once the round has a result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, GodotConvert, Var, Export, Serialize, Deserialize)]
+#[godot(via = i64)]
+pub enum EndReason {
+	Ko,
+	Guard,
+	Position,
+}
+
+#[cfg(test)]
+mod test {
+	use super::Match;
+	use crate::{
+		decoder::{InputDecoder, RawInput, SocdPolicy},
+		input::{FgInput, InputConfig},
+		replay::Replay,
+		rng::Rng,
+		training::DummyBehaviour,
+	};
+
+	const NONE: FgInput = FgInput::new(0, 0, false, false);
+	const ATTACK: FgInput = FgInput::new(0, 0, true, false);
+
+	/// Random blocking dummy, attacked over and over
+	fn play(sim: &mut Match, frames: usize) {
+		for i in 0..frames {
+			let input = if i % 40 == 0 { ATTACK } else { NONE };
+			sim.step(input, NONE);
+			if i % 40 == 39 {
+				sim.reset_positions();
+			}
+		}
+	}
+
+	fn training(seed: i64) -> Match {
+		let mut sim = Match::new(InputConfig::human(), InputConfig::human());
+		sim.set_seed(seed);
+		sim.set_training(false, DummyBehaviour::RandomBlock);
+		sim.set_reset_spacing(250, Match::STAGE_LEN / 2);
+		sim.reset_positions();
+		sim
+	}
+
+	#[test]
+	fn snapshot() {
+		let mut sim = training(7);
+		play(&mut sim, 100);
+
+		let mut snapshot = sim.clone();
+		assert_eq!(snapshot.state_hash(), sim.state_hash());
+
+		play(&mut sim, 1000);
+		play(&mut snapshot, 1000);
+		assert_eq!(snapshot.state_hash(), sim.state_hash());
+	}
+
+	#[test]
+	fn seeds() {
+		let hashes = |seed| {
+			let mut sim = training(seed);
+			(0..25)
+				.map(|_| {
+					play(&mut sim, 40);
+					sim.state_hash()
+				})
+				.collect::<Vec<_>>()
+		};
+
+		assert_eq!(hashes(1), hashes(1));
+		assert_ne!(hashes(1), hashes(2));
+	}
+
+	#[test]
+	fn hashed_settings() {
+		let mut sim = Match::new(InputConfig::human(), InputConfig::human());
+		sim.set_input_delay(true, 5, 0);
+		let mut delayed = sim.clone();
+		delayed.step(ATTACK, NONE);
+		sim.step(NONE, NONE);
+		assert_ne!(sim.state_hash(), delayed.state_hash());
+
+		let mut randomized = sim.clone();
+		randomized.enable_domain_randomization();
+		assert_ne!(sim.state_hash(), randomized.state_hash());
+
+		let mut training = sim.clone();
+		training.set_training(false, DummyBehaviour::Stand);
+		assert_ne!(sim.state_hash(), training.state_hash());
+	}
+
+	#[test]
+	fn replay_settings() {
+		let mut rng = Rng::new(5);
+		let mut sim = Match::new(InputConfig::human(), InputConfig::human());
+		sim.set_seed(9);
+		sim.set_input_delay(false, 2, 3);
+		sim.enable_domain_randomization();
+		sim.set_random_spacing(200, 900);
+		sim.set_input_drop_chance(0.2);
+
+		let mut replay = Replay::new(InputConfig::human(), InputConfig::human());
+		assert!(replay.copy_settings(&sim));
+		replay.set_seed(9);
+
+		let mut hashes = Vec::new();
+		while sim.continues() {
+			let [input1, input2] = [(); 2].map(|_| {
+				FgInput::new(rng.range(-1, 1) as i8, 0, rng.chance(0.1), false)
+			});
+			replay.push(input1, input2);
+			let res = sim.step(input1, input2);
+			hashes.push(sim.state_hash());
+
+			if !matches!(res, super::Result::Continue | super::Result::Pause) {
+				sim.new_round();
+			}
+		}
+
+		let mut replayed = Vec::new();
+		replay.play(|sim, _| replayed.push(sim.state_hash()));
+		assert_eq!(replayed, hashes);
+
+		sim.set_training(false, DummyBehaviour::Stand);
+		assert!(!replay.copy_settings(&sim));
+	}
+
+	#[test]
+	fn mirrored() {
+		let mut rng = Rng::new(3);
+		let mut decoders = [(); 2].map(|_| InputDecoder::new(SocdPolicy::Neutral));
+		let mut sim = Match::new(InputConfig::bot(), InputConfig::human());
+		let mut mirrored = sim.mirrored();
+
+		for _ in 0..5000 {
+			let [input1, input2] = decoders.each_mut().map(|decoder| {
+				let raw = RawInput::new(rng.chance(0.4), rng.chance(0.3), rng.chance(0.1));
+				decoder.decode(raw)
+			});
+
+			let res = sim.step(input1, input2);
+			assert_eq!(mirrored.step(input2, input1), res.mirrored());
+			assert_eq!(mirrored.state_hash(), sim.mirrored().state_hash());
+			assert_eq!(mirrored.player_obs(false), sim.player_obs(true));
+
+			if matches!(
+				res,
+				super::Result::Player1 | super::Result::Player2 | super::Result::Draw
+			) {
+				sim.new_round();
+				mirrored.new_round();
+			}
+		}
+
+		assert!(sim.p1_wins() + sim.p2_wins() > 0);
+	}
+
+	#[test]
+	fn obs_into() {
+		let mut sim = training(3);
+		play(&mut sim, 130);
+
+		for p1 in [true, false] {
+			let mut out = [-1.0; Match::PLAYER_OBS_LEN + 1];
+			sim.player_obs_into(p1, &mut out);
+			assert_eq!(out[..Match::PLAYER_OBS_LEN], sim.player_obs(p1));
+			assert_eq!(out[Match::PLAYER_OBS_LEN], -1.0);
+
+			let mut out = [-1.0; Match::PUNISH_OBS_LEN];
+			sim.punish_obs_into(p1, &mut out);
+			assert_eq!(out[..], sim.punish_obs(p1));
+		}
+	}
+
+	#[test]
+	fn player_sound() {
+		let mut sim = training(5);
+		let mut played = 0;
+
+		for i in 0..2000 {
+			let input = if i % 40 == 0 { ATTACK } else { NONE };
+			sim.step(input, NONE);
+			if i % 40 == 39 {
+				sim.reset_positions();
+			}
+
+			let sounds: Vec<_> = [true, false]
+				.into_iter()
+				.filter_map(|p1| usize::try_from(sim.player_sound(p1)).ok())
+				.map(|id| Match::SOUNDS[id])
+				.collect();
+			assert_eq!(sounds, sim.audio_names().collect::<Vec<_>>());
+			played += sounds.len();
+		}
+
+		assert!(played > 0);
+	}
+}
@@ -0,0 +1,468 @@
+use std::mem;
+
+use godot::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+	player::PlayerState,
+	simul::{self, EndReason, Match},
+};
+
+/// Attacks started, counted per move.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveCounts {
+	pub nnormal: u32,
+	pub mnormal: u32,
+	pub nspecial: u32,
+	pub mspecial: u32,
+}
+
+impl MoveCounts {
+	pub fn to_dictionary(self) -> Dictionary {
+		let mut res = Dictionary::new();
+
+		res.set("nnormal", self.nnormal);
+		res.set("mnormal", self.mnormal);
+		res.set("nspecial", self.nspecial);
+		res.set("mspecial", self.mspecial);
+
+		res
+	}
+}
+
+/// Everything counted from the point of view of the player that did it,
+/// e.g. `hits` are the hits landed, `high_blocks` are the attacks blocked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerStats {
+	pub moves: MoveCounts,
+	pub hits: u32,
+	pub counter_hits: u32,
+	pub high_blocks: u32,
+	pub low_blocks: u32,
+	pub guard_breaks: u32,
+	pub kos: u32,
+	pub whiffs: u32,
+	pub fdashes: u32,
+	pub bdashes: u32,
+	pub cornered_frames: u32,
+	pub charge_releases: u32,
+	pub rounds_won: u32,
+}
+
+impl PlayerStats {
+	pub fn to_dictionary(self) -> Dictionary {
+		let mut res = Dictionary::new();
+
+		res.set("moves", self.moves.to_dictionary());
+		res.set("hits", self.hits);
+		res.set("counter_hits", self.counter_hits);
+		res.set("high_blocks", self.high_blocks);
+		res.set("low_blocks", self.low_blocks);
+		res.set("guard_breaks", self.guard_breaks);
+		res.set("kos", self.kos);
+		res.set("whiffs", self.whiffs);
+		res.set("fdashes", self.fdashes);
+		res.set("bdashes", self.bdashes);
+		res.set("cornered_frames", self.cornered_frames);
+		res.set("charge_releases", self.charge_releases);
+		res.set("rounds_won", self.rounds_won);
+
+		res
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoundRecord {
+	pub result: simul::Result,
+	pub reason: EndReason,
+	pub frames: u32,
+}
+
+impl RoundRecord {
+	pub fn to_dictionary(self) -> Dictionary {
+		let mut res = Dictionary::new();
+
+		res.set("result", self.result);
+		res.set("reason", self.reason);
+		res.set("frames", self.frames);
+
+		res
+	}
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, GodotClass)]
+#[class(no_init)]
+pub struct MatchStats {
+	pub player1: PlayerStats,
+	pub player2: PlayerStats,
+	pub rounds: Vec<RoundRecord>,
+	/// Frames the game was in `GameState::Active`
+	pub active_frames: u32,
+	pub distance_total: u64,
+	#[serde(skip)]
+	round_frames: u32,
+	#[serde(skip)]
+	prev_states: Option<(PlayerState, PlayerState)>,
+}
+
+#[godot_api]
+impl MatchStats {
+	/// Same threshold the corner penalty uses in `battle_scene.gd`
+	pub const CORNER_DIST: i16 = 200;
+
+	#[func]
+	pub fn gd_new() -> Gd<Self> {
+		Gd::from_object(Self::new())
+	}
+
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	#[func]
+	pub fn gd_record(&mut self, sim: Gd<Match>, result: simul::Result) {
+		self.record(&sim.bind(), result);
+	}
+
+	/// Must be called after every `Match::frame_update`, with its result.
+	pub fn record(&mut self, sim: &Match, result: simul::Result) {
+		let states = (sim.player(true).state(), sim.player(false).state());
+		let prev = self.prev_states.unwrap_or(states);
+
+		if let Some(state) = changed(prev.0, states.0) {
+			Self::record_own(&mut self.player1, prev.0, state);
+			Self::record_opponent(
+				&mut self.player2,
+				state,
+				sim.player(true).counter_hit,
+			);
+		}
+		if let Some(state) = changed(prev.1, states.1) {
+			Self::record_own(&mut self.player2, prev.1, state);
+			Self::record_opponent(
+				&mut self.player1,
+				state,
+				sim.player(false).counter_hit,
+			);
+		}
+
+		self.prev_states = Some(states);
+
+		if sim.is_active() {
+			self.active_frames += 1;
+			self.round_frames += 1;
+			self.distance_total += sim.player_distance() as u64;
+
+			if sim.player_relative_pos(true) < Self::CORNER_DIST {
+				self.player1.cornered_frames += 1;
+			}
+			if sim.player_relative_pos(false) < Self::CORNER_DIST {
+				self.player2.cornered_frames += 1;
+			}
+		}
+
+		match result {
+			simul::Result::Continue | simul::Result::Pause | simul::Result::Timeout => (),
+			simul::Result::Player1 | simul::Result::Player2 | simul::Result::Draw => {
+				if matches!(result, simul::Result::Player1 | simul::Result::Draw) {
+					self.player1.rounds_won += 1;
+				}
+				if matches!(result, simul::Result::Player2 | simul::Result::Draw) {
+					self.player2.rounds_won += 1;
+				}
+
+				self.rounds.push(RoundRecord {
+					result,
+					reason: sim.end_reason(),
+					frames: self.round_frames,
+				});
+				self.round_frames = 0;
+				self.prev_states = None;
+			}
+		}
+	}
+
+	/// `PlayerStats` of the player as a dictionary with the same keys, the move counts are
+	/// under `moves`.
+	#[func]
+	pub fn player_stats(&self, p1: bool) -> Dictionary {
+		match p1 {
+			true => self.player1.to_dictionary(),
+			false => self.player2.to_dictionary(),
+		}
+	}
+
+	/// Every finished round, oldest first, with `result`, `reason` and `frames` keys.
+	#[func]
+	pub fn round_records(&self) -> Array<Dictionary> {
+		self.rounds
+			.iter()
+			.copied()
+			.map(RoundRecord::to_dictionary)
+			.collect()
+	}
+
+	#[func]
+	pub fn active_frames(&self) -> i64 {
+		self.active_frames as i64
+	}
+
+	#[func]
+	pub fn average_spacing(&self) -> f32 {
+		match self.active_frames {
+			0 => 0.0,
+			frames => self.distance_total as f32 / frames as f32,
+		}
+	}
+
+	#[func]
+	pub fn gd_mirrored(&self) -> Gd<Self> {
+		Gd::from_object(self.mirrored())
+	}
+
+	/// Same stats with the players swapped.
+	pub fn mirrored(&self) -> Self {
+		MatchStats {
+			player1: self.player2,
+			player2: self.player1,
+			rounds: self
+				.rounds
+				.iter()
+				.map(|round| RoundRecord {
+					result: round.result.mirrored(),
+					..*round
+				})
+				.collect(),
+			prev_states: self.prev_states.map(|(p1, p2)| (p2, p1)),
+			..self.clone()
+		}
+	}
+
+	#[func]
+	pub fn to_json(&self) -> GString {
+		// Only plain numbers and enums, serialization can not fail
+		GString::from(serde_json::to_string(self).unwrap())
+	}
+
+	#[func]
+	pub fn from_json(json: GString) -> Option<Gd<Self>> {
+		serde_json::from_str(&json.to_string())
+			.ok()
+			.map(Gd::from_object)
+	}
+
+	/// Moves started and defensive events, `state` is newly entered.
+	fn record_own(stats: &mut PlayerStats, prev: PlayerState, state: PlayerState) {
+		let from_neutral = matches!(
+			prev,
+			PlayerState::Idle(_) | PlayerState::FWalk(_) | PlayerState::BWalk(_)
+		);
+
+		match state {
+			PlayerState::NNormal(0, _) => stats.moves.nnormal += 1,
+			PlayerState::MNormal(0, _) => stats.moves.mnormal += 1,
+			PlayerState::NSpecial(0, _) => stats.moves.nspecial += 1,
+			PlayerState::MSpecial(0, _) => stats.moves.mspecial += 1,
+			PlayerState::FDash(0) => stats.fdashes += 1,
+			PlayerState::BDash(0) => stats.bdashes += 1,
+			PlayerState::HBlock(0, _) => stats.high_blocks += 1,
+			PlayerState::LBlock(0, _) => stats.low_blocks += 1,
+			_ => (),
+		}
+
+		// Specials only come out of neutral when charged, cancels come from normals
+		if from_neutral
+			&& matches!(
+				state,
+				PlayerState::NSpecial(0, _) | PlayerState::MSpecial(0, _)
+			) {
+			stats.charge_releases += 1;
+		}
+
+		// The attack is left for any other state, getting hit out of it included
+		let whiffed = matches!(
+			prev,
+			PlayerState::NNormal(_, false)
+				| PlayerState::MNormal(_, false)
+				| PlayerState::NSpecial(_, false)
+				| PlayerState::MSpecial(_, false)
+		);
+		let started = matches!(
+			state,
+			PlayerState::NNormal(0, _)
+				| PlayerState::MNormal(0, _) | PlayerState::NSpecial(0, _)
+				| PlayerState::MSpecial(0, _)
+		);
+		let left = started || mem::discriminant(&prev) != mem::discriminant(&state);
+		if whiffed && left {
+			stats.whiffs += 1;
+		}
+	}
+
+	/// Offensive events, `state` is newly entered by the opponent of `stats`' owner.
+	fn record_opponent(stats: &mut PlayerStats, state: PlayerState, counter_hit: bool) {
+		match state {
+			PlayerState::Hit(0) => {
+				stats.hits += 1;
+				if counter_hit {
+					stats.counter_hits += 1;
+				}
+			}
+			PlayerState::GuardBreak(0) => stats.guard_breaks += 1,
+			PlayerState::Dead(false) => stats.kos += 1,
+			_ => (),
+		}
+	}
+}
+
+#[inline]
+fn changed(prev: PlayerState, state: PlayerState) -> Option<PlayerState> {
+	(prev != state).then_some(state)
+}
+
+#[cfg(test)]
+mod test {
+	use super::{MatchStats, PlayerStats};
+	use crate::{
+		input::{FgInput, InputConfig},
+		player::PlayerState,
+		simul::{self, Match},
+	};
+
+	const NONE: FgInput = FgInput::new(0, 0, false, false);
+
+	fn run(
+		sim: &mut Match,
+		stats: &mut MatchStats,
+		input1: FgInput,
+		input2: FgInput,
+	) -> simul::Result {
+		let res = sim.step(input1, input2);
+		stats.record(sim, res);
+		res
+	}
+
+	#[test]
+	fn whiffs_and_dashes() {
+		let mut sim = Match::new(InputConfig::human(), InputConfig::human());
+		let mut stats = MatchStats::new();
+
+		while !sim.is_active() {
+			run(&mut sim, &mut stats, NONE, NONE);
+		}
+
+		// Players start too far to hit each other
+		run(&mut sim, &mut stats, FgInput::new(0, 0, true, true), NONE);
+		for _ in 0..40 {
+			run(&mut sim, &mut stats, NONE, NONE);
+		}
+
+		run(
+			&mut sim,
+			&mut stats,
+			NONE,
+			FgInput::new(-1, -1, false, false),
+		);
+		run(&mut sim, &mut stats, NONE, NONE);
+		run(
+			&mut sim,
+			&mut stats,
+			NONE,
+			FgInput::new(-1, -1, false, false),
+		);
+		for _ in 0..40 {
+			run(&mut sim, &mut stats, NONE, NONE);
+		}
+
+		assert_eq!(stats.player1.moves.nnormal, 1);
+		assert_eq!(stats.player1.whiffs, 1);
+		assert_eq!(stats.player1.hits, 0);
+		assert_eq!(stats.player2.bdashes, 1);
+		assert_eq!(stats.player2.fdashes, 0);
+		assert_eq!(stats.player1.charge_releases, 0);
+		assert!(stats.average_spacing() > 0.0);
+	}
+
+	#[test]
+	fn whiff_exits() {
+		let whiffs = |prev, state| {
+			let mut stats = PlayerStats::default();
+			MatchStats::record_own(&mut stats, prev, state);
+			stats.whiffs
+		};
+
+		assert_eq!(
+			whiffs(PlayerState::NNormal(3, false), PlayerState::Idle(0)),
+			1
+		);
+		assert_eq!(
+			whiffs(PlayerState::MNormal(3, false), PlayerState::Hit(0)),
+			1
+		);
+		assert_eq!(
+			whiffs(PlayerState::NSpecial(3, false), PlayerState::GuardBreak(0)),
+			1
+		);
+		assert_eq!(
+			whiffs(PlayerState::MSpecial(3, false), PlayerState::Dead(false)),
+			1
+		);
+		assert_eq!(
+			whiffs(
+				PlayerState::NNormal(9, false),
+				PlayerState::NNormal(0, false)
+			),
+			1
+		);
+
+		assert_eq!(
+			whiffs(
+				PlayerState::NNormal(3, false),
+				PlayerState::NNormal(4, false)
+			),
+			0
+		);
+		assert_eq!(
+			whiffs(
+				PlayerState::NNormal(3, false),
+				PlayerState::NNormal(4, true)
+			),
+			0
+		);
+		assert_eq!(
+			whiffs(
+				PlayerState::NNormal(9, true),
+				PlayerState::NSpecial(0, false)
+			),
+			0
+		);
+		assert_eq!(
+			whiffs(PlayerState::NNormal(9, true), PlayerState::Hit(0)),
+			0
+		);
+	}
+
+	#[test]
+	fn timeout_round() {
+		let mut sim = Match::new(InputConfig::human(), InputConfig::human());
+		let mut stats = MatchStats::new();
+
+		let res = loop {
+			match run(&mut sim, &mut stats, NONE, NONE) {
+				simul::Result::Continue | simul::Result::Pause => (),
+				res => break res,
+			}
+		};
+
+		assert_eq!(res, simul::Result::Draw);
+		assert_eq!(stats.rounds.len(), 1);
+		assert_eq!(stats.rounds[0].reason, simul::EndReason::Position);
+		assert_eq!(stats.player1.rounds_won, 1);
+		assert_eq!(stats.player2.rounds_won, 1);
+
+		let json = serde_json::to_string(&stats).unwrap();
+		let back: MatchStats = serde_json::from_str(&json).unwrap();
+		assert_eq!(back.rounds, stats.rounds);
+		assert_eq!(back.player1, stats.player1);
+	}
+}
@@ -0,0 +1,334 @@
+use std::hash::{Hash, Hasher};
+
+use godot::prelude::*;
+
+use crate::{
+	advantage::FrameAdvantage,
+	input::FgInput,
+	player::{Player, PlayerState},
+	recorder::InputRecorder,
+	rng::Rng,
+	simul::Match,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, GodotConvert, Var, Export)]
+#[godot(via = i64)]
+pub enum DummyBehaviour {
+	Stand,
+	BlockAll,
+	/// Stands until it gets hit, then blocks until it is left alone for a while
+	BlockAfterFirstHit,
+	/// Decides whether to block once per attack of the opponent
+	RandomBlock,
+	/// The dummy is controlled by the opponent's input while it is recorded,
+	/// the opponent stands still
+	Record,
+	/// Plays the recorded slots, see `InputRecorder`
+	Playback,
+}
+
+#[derive(Debug, Clone)]
+pub struct Dummy {
+	pub behaviour: DummyBehaviour,
+	pub block_chance: f32,
+	guarding: bool,
+	calm_frames: u8,
+	block_roll: Option<bool>,
+	pub recorder: InputRecorder,
+	record_slot: usize,
+}
+
+impl Dummy {
+	/// Frames without pressure before `BlockAfterFirstHit` stops blocking
+	const CALM_TIME: u8 = 30;
+	const NEUTRAL: FgInput = FgInput::new(0, 0, false, false);
+	// No press, so blocking never turns into a back dash
+	const BLOCK: FgInput = FgInput::new(-1, 0, false, false);
+
+	pub const fn new(behaviour: DummyBehaviour) -> Self {
+		Dummy {
+			behaviour,
+			block_chance: 0.5,
+			guarding: false,
+			calm_frames: 0,
+			block_roll: None,
+			recorder: InputRecorder::new(InputRecorder::DEFAULT_MAX_FRAMES),
+			record_slot: 0,
+		}
+	}
+
+	pub fn set_behaviour(&mut self, rng: &mut Rng, behaviour: DummyBehaviour) {
+		match behaviour {
+			DummyBehaviour::Record => self.recorder.start_recording(self.record_slot),
+			DummyBehaviour::Playback => self.recorder.start_playback(rng),
+			_ => (),
+		}
+
+		self.behaviour = behaviour;
+		self.guarding = false;
+		self.calm_frames = 0;
+		self.block_roll = None;
+	}
+
+	/// Slot that the next `DummyBehaviour::Record` records into. Returns false and keeps the old
+	/// slot unless it is below `InputRecorder::SLOTS`.
+	pub fn set_record_slot(&mut self, slot: i64) -> bool {
+		let valid = (0..InputRecorder::SLOTS as i64).contains(&slot);
+		if valid {
+			self.record_slot = slot as usize;
+		}
+
+		valid
+	}
+
+	/// Returns the input of the dummy and the input of the opponent, in this order.
+	pub fn input(
+		&mut self,
+		rng: &mut Rng,
+		dummy: &Player,
+		opponent: &Player,
+		opp_input: FgInput,
+	) -> (FgInput, FgInput) {
+		let block = match self.behaviour {
+			DummyBehaviour::Stand => false,
+			DummyBehaviour::BlockAll => opponent.is_attacking(),
+			DummyBehaviour::BlockAfterFirstHit => {
+				if matches!(
+					dummy.state(),
+					PlayerState::Hit(_) | PlayerState::GuardBreak(_)
+				) {
+					self.guarding = true;
+				}
+
+				if dummy.can_attack() && !opponent.is_attacking() {
+					self.calm_frames = self.calm_frames.saturating_add(1);
+				} else {
+					self.calm_frames = 0;
+				}
+
+				if self.calm_frames >= Self::CALM_TIME {
+					self.guarding = false;
+				}
+
+				self.guarding && opponent.is_attacking()
+			}
+			DummyBehaviour::RandomBlock => {
+				if opponent.is_attacking() {
+					let chance = self.block_chance;
+					*self.block_roll.get_or_insert_with(|| rng.chance(chance))
+				} else {
+					self.block_roll = None;
+					false
+				}
+			}
+			DummyBehaviour::Record => {
+				self.recorder.record(opp_input);
+				return (opp_input, Self::NEUTRAL);
+			}
+			DummyBehaviour::Playback => match self.recorder.next_input(rng) {
+				Some(input) => return (input, opp_input),
+				None => false,
+			},
+		};
+
+		match block {
+			true => (Self::BLOCK, opp_input),
+			false => (Self::NEUTRAL, opp_input),
+		}
+	}
+}
+
+impl Hash for Dummy {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		(
+			self.behaviour,
+			self.block_chance.to_bits(),
+			self.guarding,
+			self.calm_frames,
+			self.block_roll,
+			&self.recorder,
+			self.record_slot,
+		)
+			.hash(state);
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct TrainingMode {
+	pub dummy: Dummy,
+	pub advantage: FrameAdvantage,
+	pub dummy_p1: bool,
+	pub infinite_guard: bool,
+	pub unlimited_time: bool,
+	/// Distance between the players after `Match::reset_positions`
+	pub spacing: i16,
+	/// Midpoint of the players after `Match::reset_positions`
+	pub center: i16,
+}
+
+impl TrainingMode {
+	pub const fn new(dummy_p1: bool, dummy: Dummy, spacing: i16, center: i16) -> Self {
+		TrainingMode {
+			dummy,
+			advantage: FrameAdvantage::new(),
+			dummy_p1,
+			infinite_guard: false,
+			unlimited_time: false,
+			spacing,
+			center,
+		}
+	}
+
+	/// Same training with the players swapped, the reset positions are mirrored too.
+	pub fn mirrored(&self) -> Self {
+		TrainingMode {
+			dummy: self.dummy.clone(),
+			advantage: self.advantage.mirrored(),
+			dummy_p1: !self.dummy_p1,
+			infinite_guard: self.infinite_guard,
+			unlimited_time: self.unlimited_time,
+			spacing: self.spacing,
+			center: Match::STAGE_LEN - self.center,
+		}
+	}
+
+	/// Returns false and keeps the old values unless both players fit on the stage, `spacing`
+	/// apart around `center`.
+	pub fn set_spacing(&mut self, spacing: i16, center: i16) -> bool {
+		// Same positions as `Match::reset_positions`
+		let stage = 0..=Match::STAGE_LEN as i32;
+		let p1_pos = center as i32 - spacing as i32 / 2;
+		let p2_pos = p1_pos + spacing as i32;
+		let valid = spacing >= 0 && stage.contains(&p1_pos) && stage.contains(&p2_pos);
+		if valid {
+			self.spacing = spacing;
+			self.center = center;
+		}
+
+		valid
+	}
+
+	/// Replaces the input of the dummy, may also replace the other player's input.
+	pub fn inputs(
+		&mut self,
+		rng: &mut Rng,
+		player1: &Player,
+		player2: &Player,
+		input1: FgInput,
+		input2: FgInput,
+	) -> (FgInput, FgInput) {
+		match self.dummy_p1 {
+			true => self.dummy.input(rng, player1, player2, input2),
+			false => {
+				let (dummy, opponent) =
+					self.dummy.input(rng, player2, player1, input1);
+				(opponent, dummy)
+			}
+		}
+	}
+}
+
+/// Frame advantage is left out, it only reports on the match.
+impl Hash for TrainingMode {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		(
+			&self.dummy,
+			self.dummy_p1,
+			self.infinite_guard,
+			self.unlimited_time,
+			self.spacing,
+			self.center,
+		)
+			.hash(state);
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{Dummy, DummyBehaviour, TrainingMode};
+	use crate::{
+		input::{FgInput, InputConfig},
+		recorder::InputRecorder,
+		simul::Match,
+	};
+
+	const NONE: FgInput = FgInput::new(0, 0, false, false);
+	const ATTACK: FgInput = FgInput::new(0, 0, true, true);
+
+	fn attacked_state(behaviour: DummyBehaviour) -> i64 {
+		let mut sim = Match::new(InputConfig::human(), InputConfig::human());
+		sim.set_training(false, behaviour);
+		sim.set_reset_spacing(250, Match::STAGE_LEN / 2);
+		sim.reset_positions();
+
+		sim.step(ATTACK, NONE);
+		for _ in 0..20 {
+			sim.step(NONE, NONE);
+			// Past idle and walking
+			if sim.player_state(false) > 2 {
+				break;
+			}
+		}
+
+		sim.player_state(false)
+	}
+
+	#[test]
+	fn block_all() {
+		// Block
+		assert_eq!(attacked_state(DummyBehaviour::BlockAll), 5);
+		// Hit
+		assert_eq!(attacked_state(DummyBehaviour::Stand), 7);
+		assert_eq!(attacked_state(DummyBehaviour::BlockAfterFirstHit), 7);
+	}
+
+	#[test]
+	fn reset_spacing() {
+		let mut training =
+			TrainingMode::new(false, Dummy::new(DummyBehaviour::Stand), 250, 700);
+		assert!(!training.set_spacing(-300, 400));
+		assert!(!training.set_spacing(300, Match::STAGE_LEN + 1));
+		// Would push a player past the wall
+		assert!(!training.set_spacing(1000, 100));
+		assert!(!training.set_spacing(400, Match::STAGE_LEN - 100));
+		assert_eq!((training.spacing, training.center), (250, 700));
+
+		assert!(training.set_spacing(300, 400));
+		assert_eq!((training.spacing, training.center), (300, 400));
+	}
+
+	#[test]
+	fn record_slot() {
+		let mut dummy = Dummy::new(DummyBehaviour::Stand);
+		assert!(dummy.set_record_slot(2));
+		assert!(!dummy.set_record_slot(-1));
+		assert!(!dummy.set_record_slot(InputRecorder::SLOTS as i64));
+		assert_eq!(dummy.record_slot, 2);
+
+		assert!(dummy.set_record_slot(InputRecorder::SLOTS as i64 - 1));
+		assert_eq!(dummy.record_slot, InputRecorder::SLOTS - 1);
+	}
+
+	#[test]
+	fn record_playback() {
+		let mut sim = Match::new(InputConfig::human(), InputConfig::human());
+		sim.set_training(false, DummyBehaviour::Record);
+		sim.reset_positions();
+
+		let fwalk = FgInput::new(1, 1, false, false);
+		let (p1_start, p2_start) = (sim.p1_pos(), sim.p2_pos());
+		for _ in 0..10 {
+			sim.step(fwalk, fwalk);
+		}
+		// The recording player stands still, the dummy walks forward
+		assert_eq!(sim.p1_pos(), p1_start);
+		assert!(sim.p2_pos() < p2_start);
+
+		sim.set_dummy_behaviour(DummyBehaviour::Playback);
+		sim.reset_positions();
+		for _ in 0..10 {
+			sim.step(NONE, NONE);
+		}
+		assert!(sim.p2_pos() < p2_start);
+	}
+}